@@ -12,7 +12,7 @@ async fn main() -> anyhow::Result<()> {
     {
         http.begin_request(http_chunked::Method::Get).await?;
         {
-            http.request_header(http.host_header()).await?;
+            http.request_header(http.host_header()?).await?;
             http.request_header(HttpHeader::from_name_value("Foo", "Bar")?)
                 .await?;
             http.request_header(HttpHeader::from_name_value("Hello", "World")?)
@@ -56,5 +56,6 @@ async fn main() -> anyhow::Result<()> {
         http.response_end();
     }
     http.end();
-    Ok(println!("OK."))
+    println!("OK.");
+    Ok(())
 }
@@ -4,12 +4,12 @@ const HOST: &str = "http://anglesharp.azurewebsites.net/Chunked";
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
     let mut http = http_chunked::http::Context::new(HOST).await?;
-    println!("We have http: {:?} (Host={:?})", http.debug(), http.host_header());
+    println!("We have http: {:?} (Host={:?})", http.debug(), http.host_header()?);
     http.begin();
     {
         http.begin_request(http_chunked::Method::Get).await?;
         {
-            http.request_header(http.host_header()).await?;
+            http.request_header(http.host_header()?).await?;
             http.request_header(HttpHeader::from_name_value("Foo", "Bar")?)
                 .await?;
             http.request_header(HttpHeader::from_name_value("Hello", "World")?)
@@ -31,11 +31,13 @@ async fn main() -> anyhow::Result<()> {
             println!("{:?}", header);
         }
         println!("{}", "-".repeat(40));
+        let mut decoder = http.text_decoder();
         let mut buf = [0; 4096];
         for i in 1.. {
             let n = http.response_body_chunk_read(&mut buf).await?;
-            println!("Chunk {}. {:?}", i, std::str::from_utf8(&buf[..n]));
-            if !http.has_response() {
+            let last = !http.has_response();
+            println!("Chunk {}. {:?}", i, decoder.decode(&buf[..n], last));
+            if last {
                 break;
             }
         }
@@ -44,5 +46,6 @@ async fn main() -> anyhow::Result<()> {
         http.response_end();
     }
     http.end();
-    Ok(println!("Ok."))
+    println!("Ok.");
+    Ok(())
 }
@@ -7,7 +7,7 @@ async fn main() -> anyhow::Result<()> {
     let http = http_chunked::http::Context::new(HOST)
         .await
         .context("create http context")?;
-    dbg!(http.host_header());
-    dbg!(http.host_header().to_string());
+    dbg!(http.host_header()?);
+    dbg!(http.host_header()?.to_string());
     Ok(())
 }
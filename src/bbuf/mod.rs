@@ -1,4 +1,4 @@
-use crate::Socket;
+use crate::{http::limits::LimitError, Socket};
 use anyhow::Context;
 use std::ops::AddAssign;
 use tokio::io::{AsyncReadExt, AsyncWriteExt};
@@ -36,23 +36,44 @@ impl<S: Socket> Buffer<S> {
             .context("write some bytes")
     }
 
-    pub async fn read_line(&mut self) -> anyhow::Result<Vec<u8>> {
-        self.read_until_and_chop(b"\r\n").await
+    pub async fn read_line(&mut self, limit: usize) -> anyhow::Result<Vec<u8>> {
+        self.read_until_and_chop(b"\r\n", limit, LimitError::LineTooLong)
+            .await
     }
 
-    pub async fn read_until_and_chop(&mut self, delim: &[u8]) -> anyhow::Result<Vec<u8>> {
+    /// Fails with `overflow` when the delimiter is not found within `limit` bytes.
+    pub async fn read_until_and_chop(
+        &mut self,
+        delim: &[u8],
+        limit: usize,
+        overflow: LimitError,
+    ) -> anyhow::Result<Vec<u8>> {
         let mut result = vec![];
         loop {
             match self.end_of_line(delim) {
                 Some(end_of_line) => {
+                    if result.len() + end_of_line > limit {
+                        return Err(overflow.into());
+                    }
                     result.extend_from_slice(self.slice(end_of_line)?);
                     self.shift_buffer(end_of_line + delim.len())
                         .context("reach the unreachable: buffer is shorter than expected")?;
                     break;
                 }
                 None => {
-                    result.extend_from_slice(self.buffer());
-                    self.refill_buffer().await?;
+                    // The tail may be the start of a delimiter split between two reads.
+                    let keep = self.buffer().len().min(delim.len().saturating_sub(1));
+                    let take = self.buffer().len() - keep;
+                    result.extend_from_slice(self.slice(take)?);
+                    self.shift_buffer(take)?;
+                    if result.len() > limit {
+                        return Err(overflow.into());
+                    }
+                    if self.refill_buffer().await? == 0 {
+                        return Err(anyhow::Error::msg(
+                            "connection closed before the end of line",
+                        ));
+                    }
                 }
             }
         }
@@ -89,6 +110,14 @@ impl<S: Socket> Buffer<S> {
         Ok(result)
     }
 
+    /// Waits for bytes when the buffer is empty, `false` once the peer has closed the connection.
+    pub async fn has_data(&mut self) -> anyhow::Result<bool> {
+        if self.buffer().is_empty() {
+            self.refill_buffer().await?;
+        }
+        Ok(!self.buffer().is_empty())
+    }
+
     pub fn buffer(&self) -> &[u8] {
         &self.inner[self.begin..self.end]
     }
@@ -98,17 +127,33 @@ impl<S: Socket> Buffer<S> {
             .get(..until)
             .ok_or_else(|| anyhow::Error::msg("slice overshoots the end of the buffer"))
     }
+
+    /// Reading from the socket directly skips the bytes already buffered.
+    #[cfg(feature = "test-util")]
+    pub fn get_mut(&mut self) -> &mut S {
+        &mut self.socket
+    }
+
+    /// The socket and the bytes read ahead from it but not consumed yet.
+    pub fn into_parts(self) -> (S, Vec<u8>) {
+        let unread = self.buffer().to_vec();
+        (self.socket, unread)
+    }
 }
 
 impl<S: Socket> Buffer<S> {
-    async fn refill_buffer(&mut self) -> anyhow::Result<()> {
-        self.end = self
+    /// Reads after the unconsumed bytes, which move to the front first.
+    async fn refill_buffer(&mut self) -> anyhow::Result<usize> {
+        self.inner.copy_within(self.begin..self.end, 0);
+        self.end -= self.begin;
+        self.begin = 0;
+        let n = self
             .socket
-            .read(&mut self.inner)
+            .read(&mut self.inner[self.end..])
             .await
             .context("refill buffer read from socket")?;
-        self.begin = 0;
-        Ok(())
+        self.end += n;
+        Ok(n)
     }
 
     fn shift_buffer(&mut self, until: usize) -> anyhow::Result<()> {
@@ -131,3 +176,33 @@ impl<S: Socket> Buffer<S> {
             .map(|(i, _)| i)
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test_util::MockSocket;
+
+    #[tokio::test]
+    async fn try_delimiter_split_between_reads() {
+        let socket = MockSocket::new()
+            .then_read("HTTP/1.1 200 OK\r\nA: b\r")
+            .then_read("\n\r")
+            .then_read("\n")
+            .then_read("body");
+        let mut buffer = Buffer::new(socket);
+        let head = buffer
+            .read_until_and_chop(b"\r\n\r\n", 1024, LimitError::HeaderSectionTooLarge)
+            .await
+            .unwrap();
+        assert_eq!(b"HTTP/1.1 200 OK\r\nA: b", head.as_slice());
+        let mut body = [0; 8];
+        assert_eq!(4, buffer.read_some_bytes(&mut body).await.unwrap());
+        assert_eq!(b"body", &body[..4]);
+
+        let mut buffer = Buffer::new(MockSocket::new().then_read("abc\r").then_read("\n"));
+        assert_eq!(b"abc", buffer.read_line(3).await.unwrap().as_slice());
+        let mut buffer = Buffer::new(MockSocket::new().then_read("abcd\r").then_read("\n"));
+        let e = buffer.read_line(3).await.unwrap_err();
+        assert_eq!(Some(&LimitError::LineTooLong), e.downcast_ref());
+    }
+}
@@ -0,0 +1,98 @@
+//! `tower::Service` on top of [`Context`].
+
+use crate::connector::{Connector, TcpConnector};
+use crate::http::Context;
+use anyhow::Context as AnyHowContext;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::Poll;
+
+/// Sends every request over a new connection and buffers the whole response body
+/// with [`Context::bytes`].
+///
+/// Request URIs must be absolute, e.g. `http://example.org/path`.
+#[derive(Debug, Default)]
+pub struct Client<C = TcpConnector> {
+    connector: Arc<C>,
+}
+
+impl<C> Clone for Client<C> {
+    fn clone(&self) -> Self {
+        Self {
+            connector: self.connector.clone(),
+        }
+    }
+}
+
+impl<C: Connector> Client<C> {
+    pub fn new(connector: C) -> Self {
+        Self {
+            connector: Arc::new(connector),
+        }
+    }
+}
+
+impl<B, C> tower_service::Service<http::Request<B>> for Client<C>
+where
+    B: AsRef<[u8]> + Send + 'static,
+    C: Connector + Send + Sync + 'static,
+    C::Socket: Send,
+{
+    type Response = http::Response<Vec<u8>>;
+    type Error = anyhow::Error;
+    type Future = Pin<Box<dyn Future<Output = anyhow::Result<Self::Response>> + Send>>;
+
+    fn poll_ready(&mut self, _cx: &mut std::task::Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, request: http::Request<B>) -> Self::Future {
+        let connector = self.connector.clone();
+        Box::pin(async move {
+            let url = url::Url::parse(&request.uri().to_string())
+                .context("request URI must be absolute")?;
+            let mut context = Context::with_connector(url, connector.as_ref()).await?;
+            let response = context.send(request).await?;
+            let (parts, _) = response.into_parts();
+            let body = context.bytes().await?;
+            Ok(http::Response::from_parts(parts, body))
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+    use tower_service::Service;
+
+    #[tokio::test]
+    async fn try_service_call() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let mut request = vec![];
+            while !request.ends_with(b"\r\n\r\n") {
+                request.push(stream.read_u8().await.unwrap());
+            }
+            stream
+                .write_all(b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n2\r\nhe\r\n3\r\nllo\r\n0\r\n\r\n")
+                .await
+                .unwrap();
+        });
+
+        let mut client = Client::new(TcpConnector::new());
+        std::future::poll_fn(|cx| Service::<http::Request<Vec<u8>>>::poll_ready(&mut client, cx))
+            .await
+            .unwrap();
+        let request = http::Request::get(format!("http://{}/greeting", addr))
+            .body(vec![])
+            .unwrap();
+        let response = client.call(request).await.unwrap();
+        assert_eq!(http::StatusCode::OK, response.status());
+        assert_eq!(b"hello", response.body().as_slice());
+    }
+}
@@ -0,0 +1,289 @@
+use crate::{Proxy, Socket, Socks5Proxy};
+use anyhow::Context;
+use std::future::Future;
+use std::net::{IpAddr, SocketAddr};
+use std::time::Duration;
+use tokio::net::{TcpSocket, TcpStream};
+
+mod resolver;
+
+pub use resolver::{Resolve, StaticResolver, SystemResolver};
+
+const HAPPY_EYEBALLS_DELAY: Duration = Duration::from_millis(250);
+
+/// Resolves and dials the host of a URL.
+pub trait Connector {
+    type Socket: Socket;
+
+    fn connect(&self, url: &url::Url) -> impl Future<Output = anyhow::Result<Self::Socket>> + Send;
+
+    /// Proxy expecting absolute-form request targets on the returned socket.
+    fn forward_proxy(&self, _url: &url::Url) -> Option<&Proxy> {
+        None
+    }
+}
+
+/// Direct TCP connections with Happy Eyeballs (RFC 8305) racing of the resolved addresses.
+#[derive(Debug, Clone)]
+pub struct TcpConnector<R = SystemResolver> {
+    resolver: R,
+    happy_eyeballs_delay: Option<Duration>,
+    local_address: Option<IpAddr>,
+}
+
+impl Default for TcpConnector {
+    fn default() -> Self {
+        Self {
+            resolver: SystemResolver,
+            happy_eyeballs_delay: Some(HAPPY_EYEBALLS_DELAY),
+            local_address: None,
+        }
+    }
+}
+
+impl TcpConnector {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl<R: Resolve> TcpConnector<R> {
+    pub fn with_resolver<T: Resolve>(self, resolver: T) -> TcpConnector<T> {
+        TcpConnector {
+            resolver,
+            happy_eyeballs_delay: self.happy_eyeballs_delay,
+            local_address: self.local_address,
+        }
+    }
+
+    /// `None` dials the addresses one after another.
+    pub fn with_happy_eyeballs(mut self, delay: Option<Duration>) -> Self {
+        self.happy_eyeballs_delay = delay;
+        self
+    }
+
+    /// Binds outgoing connections to a local address, only addresses of its family are dialed.
+    pub fn with_local_address(mut self, local_address: IpAddr) -> Self {
+        self.local_address = Some(local_address);
+        self
+    }
+
+    pub async fn connect_addrs(&self, addrs: Vec<SocketAddr>) -> anyhow::Result<TcpStream> {
+        let addrs: Vec<_> = match self.local_address {
+            Some(local) => addrs
+                .into_iter()
+                .filter(|addr| addr.is_ipv6() == local.is_ipv6())
+                .collect(),
+            None => addrs,
+        };
+        let addrs = interleave_families(addrs);
+        let local = self.local_address;
+        match self.happy_eyeballs_delay {
+            Some(delay) if addrs.len() > 1 => {
+                let mut attempts = tokio::task::JoinSet::new();
+                let mut pending = addrs.into_iter();
+                let mut last_error = None;
+                if let Some(addr) = pending.next() {
+                    attempts.spawn(dial(addr, local));
+                }
+                loop {
+                    // Restarted with every new attempt, so it counts from the latest one.
+                    let next_attempt = tokio::time::sleep(delay);
+                    tokio::select! {
+                        attempt = attempts.join_next() => {
+                            let Some(attempt) = attempt else {
+                                break;
+                            };
+                            match attempt.context("connection attempt aborted")? {
+                                Ok(stream) => return Ok(stream),
+                                Err(e) => last_error = Some(e),
+                            }
+                            // A failure starts the next attempt right away, see RFC 8305 §5.
+                            if let Some(addr) = pending.next() {
+                                attempts.spawn(dial(addr, local));
+                            }
+                        }
+                        _ = next_attempt, if pending.len() > 0 => {
+                            if let Some(addr) = pending.next() {
+                                attempts.spawn(dial(addr, local));
+                            }
+                        }
+                    }
+                }
+                Err(last_error.unwrap_or_else(|| anyhow::Error::msg("no address to connect")))
+            }
+            _ => {
+                let mut last_error = None;
+                for addr in addrs {
+                    match dial(addr, local).await {
+                        Ok(stream) => return Ok(stream),
+                        Err(e) => last_error = Some(e),
+                    }
+                }
+                Err(last_error.unwrap_or_else(|| anyhow::Error::msg("no address to connect")))
+            }
+        }
+    }
+}
+
+impl<R: Resolve + Sync> Connector for TcpConnector<R> {
+    type Socket = TcpStream;
+
+    async fn connect(&self, url: &url::Url) -> anyhow::Result<TcpStream> {
+        let (host, port) = host_port(url)?;
+        let addrs = self.resolver.resolve(host, port).await?;
+        self.connect_addrs(addrs)
+            .await
+            .context("establish connection to remote host")
+    }
+}
+
+impl Connector for Proxy {
+    type Socket = TcpStream;
+
+    async fn connect(&self, url: &url::Url) -> anyhow::Result<TcpStream> {
+        if url.scheme() == "http" {
+            Proxy::connect(self).await
+        } else {
+            let (host, port) = host_port(url)?;
+            self.tunnel(host, port).await
+        }
+    }
+
+    fn forward_proxy(&self, url: &url::Url) -> Option<&Proxy> {
+        (url.scheme() == "http").then_some(self)
+    }
+}
+
+impl Connector for Socks5Proxy {
+    type Socket = TcpStream;
+
+    async fn connect(&self, url: &url::Url) -> anyhow::Result<TcpStream> {
+        let (host, port) = host_port(url)?;
+        Socks5Proxy::connect(self, host, port).await
+    }
+}
+
+pub(crate) fn host_port(url: &url::Url) -> anyhow::Result<(&str, u16)> {
+    let host = url
+        .host_str()
+        .ok_or_else(|| anyhow::Error::msg("URL has no host"))?;
+    Ok((host, url.port_or_known_default().unwrap_or(80)))
+}
+
+async fn dial(addr: SocketAddr, local: Option<IpAddr>) -> anyhow::Result<TcpStream> {
+    match local {
+        Some(local) => {
+            let socket = if addr.is_ipv6() {
+                TcpSocket::new_v6()
+            } else {
+                TcpSocket::new_v4()
+            }
+            .context("create socket")?;
+            socket
+                .bind(SocketAddr::new(local, 0))
+                .context("bind to local address")?;
+            socket.connect(addr).await
+        }
+        None => TcpStream::connect(addr).await,
+    }
+    .with_context(|| format!("connect to {}", addr))
+}
+
+/// Alternates address families starting with the family of the first address.
+fn interleave_families(addrs: Vec<SocketAddr>) -> Vec<SocketAddr> {
+    let first_is_v6 = addrs.first().is_some_and(SocketAddr::is_ipv6);
+    let (mut preferred, mut other): (Vec<_>, Vec<_>) = addrs
+        .into_iter()
+        .partition(|addr| addr.is_ipv6() == first_is_v6);
+    let mut result = Vec::with_capacity(preferred.len() + other.len());
+    preferred.reverse();
+    other.reverse();
+    loop {
+        match (preferred.pop(), other.pop()) {
+            (None, None) => break,
+            (a, b) => result.extend(a.into_iter().chain(b)),
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::sync::Mutex;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt, DuplexStream};
+    use tokio::net::TcpListener;
+
+    struct InMemory(Mutex<Option<DuplexStream>>);
+
+    impl Connector for InMemory {
+        type Socket = DuplexStream;
+
+        async fn connect(&self, _url: &url::Url) -> anyhow::Result<DuplexStream> {
+            self.0
+                .lock()
+                .unwrap()
+                .take()
+                .ok_or_else(|| anyhow::Error::msg("already connected"))
+        }
+    }
+
+    #[test]
+    fn try_interleave_families() {
+        let addrs = ["[::1]:1", "[::2]:1", "10.0.0.1:1", "10.0.0.2:1", "[::3]:1"]
+            .map(|addr| addr.parse().unwrap())
+            .to_vec();
+        let expected: Vec<SocketAddr> =
+            ["[::1]:1", "10.0.0.1:1", "[::2]:1", "10.0.0.2:1", "[::3]:1"]
+                .map(|addr| addr.parse().unwrap())
+                .to_vec();
+        assert_eq!(expected, interleave_families(addrs));
+    }
+
+    #[tokio::test]
+    async fn try_happy_eyeballs_skips_dead_address() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let alive = listener.local_addr().unwrap();
+        let dead = {
+            let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+            listener.local_addr().unwrap()
+        };
+        let connector = TcpConnector::new()
+            .with_resolver(StaticResolver::new().add("example.org", 80, dead).add(
+                "example.org",
+                80,
+                alive,
+            ))
+            .with_local_address("127.0.0.1".parse().unwrap())
+            .with_happy_eyeballs(Some(Duration::from_secs(60)));
+        let url = url::Url::parse("http://example.org/").unwrap();
+        let stream =
+            tokio::time::timeout(Duration::from_secs(5), Connector::connect(&connector, &url))
+                .await
+                .expect("a refused attempt starts the next one without the delay")
+                .unwrap();
+        assert_eq!(alive, stream.peer_addr().unwrap());
+    }
+
+    #[tokio::test]
+    async fn try_in_memory_connector() {
+        let (client, mut server) = tokio::io::duplex(1024);
+        let connector = InMemory(Mutex::new(Some(client)));
+        let mut http = crate::http::Context::with_connector("http://example.org/", &connector)
+            .await
+            .unwrap();
+        server
+            .write_all(b"HTTP/1.1 204 No Content\r\n\r\n")
+            .await
+            .unwrap();
+        http.begin_request(crate::Method::Get).await.unwrap();
+        http.request_headers_end().await.unwrap();
+        http.response_begin().await.unwrap();
+        assert_eq!(204, http.status().unwrap().code);
+
+        let mut request = [0; 37];
+        server.read_exact(&mut request).await.unwrap();
+        assert_eq!(b"GET / HTTP/1.1\r\nHost: example.org\r\n\r\n", &request);
+    }
+}
@@ -0,0 +1,118 @@
+use anyhow::Context;
+use std::collections::HashMap;
+use std::future::Future;
+use std::net::SocketAddr;
+
+/// Turns a host name into the list of addresses to dial.
+pub trait Resolve {
+    fn resolve(
+        &self,
+        host: &str,
+        port: u16,
+    ) -> impl Future<Output = anyhow::Result<Vec<SocketAddr>>> + Send;
+}
+
+/// Resolver of the operating system (`getaddrinfo`).
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemResolver;
+
+impl Resolve for SystemResolver {
+    async fn resolve(&self, host: &str, port: u16) -> anyhow::Result<Vec<SocketAddr>> {
+        let host = host.trim_start_matches('[').trim_end_matches(']');
+        let addrs: Vec<_> = tokio::net::lookup_host((host, port))
+            .await
+            .with_context(|| format!("resolve host {:?}", host))?
+            .collect();
+        if addrs.is_empty() {
+            Err(anyhow::Error::msg(format!(
+                "host {:?} has no addresses",
+                host
+            )))
+        } else {
+            Ok(addrs)
+        }
+    }
+}
+
+/// Static host overrides in front of another resolver, like curl's `--resolve`.
+#[derive(Debug, Clone, Default)]
+pub struct StaticResolver<R = SystemResolver> {
+    overrides: HashMap<(String, u16), Vec<SocketAddr>>,
+    fallback: R,
+}
+
+impl StaticResolver {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl<R: Resolve> StaticResolver<R> {
+    pub fn with_fallback(fallback: R) -> Self {
+        Self {
+            overrides: HashMap::new(),
+            fallback,
+        }
+    }
+
+    pub fn add(mut self, host: &str, port: u16, addr: SocketAddr) -> Self {
+        self.overrides
+            .entry((host.to_ascii_lowercase(), port))
+            .or_default()
+            .push(addr);
+        self
+    }
+
+    /// Accepts curl's `host:port:addr[,addr]...` syntax.
+    pub fn add_entry(mut self, entry: &str) -> anyhow::Result<Self> {
+        let (host, rest) = entry
+            .split_once(':')
+            .ok_or_else(|| anyhow::Error::msg("resolve entry has no port"))?;
+        let (port, addrs) = rest
+            .split_once(':')
+            .ok_or_else(|| anyhow::Error::msg("resolve entry has no address"))?;
+        let port: u16 = port.parse().context("parse resolve entry port")?;
+        for addr in addrs.split(',') {
+            let ip = addr
+                .trim()
+                .trim_start_matches('[')
+                .trim_end_matches(']')
+                .parse()
+                .context("parse resolve entry address")?;
+            self = self.add(host, port, SocketAddr::new(ip, port));
+        }
+        Ok(self)
+    }
+}
+
+impl<R: Resolve + Sync> Resolve for StaticResolver<R> {
+    async fn resolve(&self, host: &str, port: u16) -> anyhow::Result<Vec<SocketAddr>> {
+        match self.overrides.get(&(host.to_ascii_lowercase(), port)) {
+            Some(addrs) => Ok(addrs.clone()),
+            None => self.fallback.resolve(host, port).await,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[tokio::test]
+    async fn try_static_resolver() {
+        let resolver = StaticResolver::new()
+            .add_entry("Example.org:443:10.0.0.1,[::1]")
+            .unwrap();
+        assert_eq!(
+            vec![
+                "10.0.0.1:443".parse::<SocketAddr>().unwrap(),
+                "[::1]:443".parse().unwrap()
+            ],
+            resolver.resolve("example.org", 443).await.unwrap()
+        );
+        assert_eq!(
+            vec!["127.0.0.1:80".parse::<SocketAddr>().unwrap()],
+            resolver.resolve("127.0.0.1", 80).await.unwrap()
+        );
+    }
+}
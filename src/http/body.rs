@@ -0,0 +1,27 @@
+use super::Context;
+use crate::Socket;
+
+/// Reader of the response body of a [`Context`].
+#[derive(Debug)]
+pub struct BodyReader<'a, S: Socket> {
+    context: &'a mut Context<S>,
+}
+
+impl<'a, S: Socket> BodyReader<'a, S> {
+    pub fn new(context: &'a mut Context<S>) -> Self {
+        Self { context }
+    }
+
+    /// Returns 0 at the end of the body.
+    pub async fn read(&mut self, buf: &mut [u8]) -> anyhow::Result<usize> {
+        if self.context.has_response() {
+            self.context.response_body_chunk_read(buf).await
+        } else {
+            Ok(0)
+        }
+    }
+
+    pub fn context(&self) -> &Context<S> {
+        self.context
+    }
+}
@@ -0,0 +1,291 @@
+//! Message body state machine shared by the client and the server side.
+
+use crate::bbuf::Buffer;
+use crate::Socket;
+use anyhow::Context as AnyHowContext;
+
+use super::framing::{Framing, Strictness, TruncatedBody};
+use super::header_map::HeaderMap;
+use super::limits::{LimitError, Limits};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum State {
+    SendingRequest,
+    Content {
+        content_length: usize,
+        bytes_read: usize,
+    },
+    Chunked {
+        chunk_size: usize,
+        bytes_read: usize,
+    },
+    UntilClose,
+    Exhausted,
+}
+
+/// Reads one body from a [`Buffer`] according to its [`Framing`].
+#[derive(Debug)]
+pub(crate) struct BodyDecoder {
+    state: State,
+    body_read: usize,
+    trailers: HeaderMap,
+}
+
+impl Default for BodyDecoder {
+    fn default() -> Self {
+        Self {
+            state: State::SendingRequest,
+            body_read: 0,
+            trailers: HeaderMap::new(),
+        }
+    }
+}
+
+impl BodyDecoder {
+    pub fn new(framing: Framing, limits: &Limits) -> anyhow::Result<Self> {
+        let state = match framing {
+            Framing::NoBody => State::Exhausted,
+            Framing::Chunked => State::Chunked {
+                chunk_size: usize::MAX,
+                bytes_read: usize::MAX,
+            },
+            Framing::ContentLength(content_length) if content_length > limits.max_body_size => {
+                return Err(LimitError::BodyTooLarge.into())
+            }
+            Framing::ContentLength(content_length) => State::Content {
+                content_length,
+                bytes_read: 0,
+            },
+            Framing::UntilClose => State::UntilClose,
+        };
+        Ok(Self {
+            state,
+            ..Self::default()
+        })
+    }
+
+    pub fn state(&self) -> State {
+        self.state
+    }
+
+    /// Trailer fields that came after the last chunk.
+    pub fn trailers(&self) -> &HeaderMap {
+        &self.trailers
+    }
+
+    pub fn has_body(&self) -> bool {
+        match self.state {
+            State::SendingRequest => false,
+            State::Content {
+                content_length,
+                bytes_read,
+            } => bytes_read < content_length,
+            State::Chunked {
+                chunk_size: _,
+                bytes_read: _,
+            } => true,
+            State::UntilClose => true,
+            State::Exhausted => false,
+        }
+    }
+
+    pub fn content_length(&self) -> Option<usize> {
+        match self.state {
+            State::Content {
+                content_length,
+                bytes_read: _,
+            } => Some(content_length),
+            _ => None,
+        }
+    }
+
+    /// Returns 0 at the end of the body, fails on a connection closed before it.
+    pub async fn read<S: Socket>(
+        &mut self,
+        buffer: &mut Buffer<S>,
+        buf: &mut [u8],
+        limits: &Limits,
+        strictness: Strictness,
+    ) -> anyhow::Result<usize> {
+        let n = self.chunk_read(buffer, buf, limits, strictness).await?;
+        self.body_read = self
+            .body_read
+            .checked_add(n)
+            .filter(|body_read| *body_read <= limits.max_body_size)
+            .ok_or(LimitError::BodyTooLarge)?;
+        Ok(n)
+    }
+
+    async fn chunk_read<S: Socket>(
+        &mut self,
+        buffer: &mut Buffer<S>,
+        buf: &mut [u8],
+        limits: &Limits,
+        strictness: Strictness,
+    ) -> anyhow::Result<usize> {
+        match self.state {
+            State::SendingRequest => Err(anyhow::Error::msg(
+                "unexpected read call while sending request",
+            )),
+            State::Content {
+                content_length: _,
+                bytes_read: _,
+            } => {
+                let n = self.get_chunk(buffer, buf).await?;
+                // An empty body reads nothing without being truncated.
+                if n == 0 && !buf.is_empty() && self.bytes_wait()? > 0 {
+                    return Err(self.truncated().into());
+                }
+                if self.bytes_wait()? == 0 {
+                    self.state = State::Exhausted;
+                }
+                Ok(n)
+            }
+            State::Chunked {
+                chunk_size: _,
+                bytes_read: _,
+            } => {
+                if self.bytes_wait()? == 0 {
+                    self.start_chunk(buffer, limits, strictness).await?;
+                }
+                let n = if self.bytes_wait().is_ok() {
+                    self.get_chunk(buffer, buf).await?
+                } else {
+                    return Ok(0);
+                };
+                if n == 0 && !buf.is_empty() {
+                    return Err(self.truncated().into());
+                }
+                if self.bytes_wait()? == 0 {
+                    end_chunk(buffer, limits).await?;
+                }
+                Ok(n)
+            }
+            State::UntilClose => {
+                let n = buffer.read_some_bytes(buf).await?;
+                if n == 0 {
+                    self.state = State::Exhausted;
+                }
+                Ok(n)
+            }
+            State::Exhausted => Err(anyhow::Error::msg("context exhausted")),
+        }
+    }
+
+    async fn get_chunk<S: Socket>(
+        &mut self,
+        buffer: &mut Buffer<S>,
+        buf: &mut [u8],
+    ) -> anyhow::Result<usize> {
+        let need = buf.len().min(self.bytes_wait()?);
+        let result = buffer.read_some_bytes(&mut buf[..need]).await?;
+        self.reduce_bytes(result)?;
+        Ok(result)
+    }
+
+    fn truncated(&self) -> TruncatedBody {
+        TruncatedBody {
+            received: self.body_read,
+            expected: self.content_length(),
+        }
+    }
+
+    fn bytes_wait(&self) -> anyhow::Result<usize> {
+        match self.state {
+            State::Content {
+                content_length,
+                bytes_read,
+            } => content_length.checked_sub(bytes_read),
+            State::Chunked {
+                chunk_size,
+                bytes_read,
+            } => chunk_size.checked_sub(bytes_read),
+            _ => return Err(anyhow::Error::msg("ask for bytes read")),
+        }
+        .ok_or_else(|| anyhow::Error::msg("read more bytes than expected"))
+    }
+
+    fn reduce_bytes(&mut self, bytes: usize) -> anyhow::Result<()> {
+        match &mut self.state {
+            State::Content {
+                content_length: _,
+                bytes_read,
+            }
+            | State::Chunked {
+                chunk_size: _,
+                bytes_read,
+            } => *bytes_read += bytes,
+            _ => return Err(anyhow::Error::msg("reduce bytes")),
+        }
+        Ok(())
+    }
+
+    async fn start_chunk<S: Socket>(
+        &mut self,
+        buffer: &mut Buffer<S>,
+        limits: &Limits,
+        strictness: Strictness,
+    ) -> anyhow::Result<()> {
+        let chunk_size_line = buffer
+            .read_line(limits.max_line_length)
+            .await
+            .context("read chunk header from socket")?;
+        let chunk_size = parse_chunk_size(&chunk_size_line, limits.max_chunk_size)?;
+        if chunk_size == 0 {
+            self.trailers = read_trailers(buffer, limits, strictness).await?;
+            self.state = State::Exhausted;
+        } else {
+            self.state = State::Chunked {
+                chunk_size,
+                bytes_read: 0,
+            };
+        }
+        Ok(())
+    }
+}
+
+async fn end_chunk<S: Socket>(buffer: &mut Buffer<S>, limits: &Limits) -> anyhow::Result<()> {
+    let rest = buffer.read_line(limits.max_line_length).await?;
+    if rest.is_empty() {
+        Ok(())
+    } else {
+        Err(anyhow::Error::msg("chunk data is longer than chunk size"))
+    }
+}
+
+async fn read_trailers<S: Socket>(
+    buffer: &mut Buffer<S>,
+    limits: &Limits,
+    strictness: Strictness,
+) -> anyhow::Result<HeaderMap> {
+    let mut trailers = vec![];
+    loop {
+        let limit = limits.max_trailer_size - trailers.len();
+        let line = buffer
+            .read_until_and_chop(b"\r\n", limit, LimitError::TrailersTooLarge)
+            .await
+            .context("read trailer section")?;
+        if line.is_empty() {
+            break;
+        }
+        if trailers.len() + line.len() + 2 > limits.max_trailer_size {
+            return Err(LimitError::TrailersTooLarge.into());
+        }
+        trailers.extend_from_slice(&line);
+        trailers.extend_from_slice(b"\r\n");
+    }
+    HeaderMap::parse_fields(&trailers, strictness)
+}
+
+/// Chunk size with chunk extensions dropped.
+fn parse_chunk_size(line: &[u8], max_chunk_size: usize) -> anyhow::Result<usize> {
+    let line = std::str::from_utf8(line).context("chunk header contains non-UTF8")?;
+    let size = line.split(';').next().unwrap_or_default().trim();
+    if size.is_empty() || !size.bytes().all(|b| b.is_ascii_hexdigit()) {
+        return Err(anyhow::Error::msg("chunk header is not hexadecimal"));
+    }
+    match usize::from_str_radix(size, 16) {
+        Ok(chunk_size) if chunk_size <= max_chunk_size => Ok(chunk_size),
+        _ => Err(LimitError::ChunkTooLarge.into()),
+    }
+}
@@ -1,11 +1,15 @@
-use self::context_state::State;
-use crate::{bbuf::Buffer, Method, Socket};
+use crate::connector::{Connector, TcpConnector};
+use crate::{bbuf::Buffer, Method, Proxy, Rewind, Socket};
 use anyhow::Context as AnyHowContext;
-use std::cell::RefCell;
-use std::ops::{AddAssign, DerefMut};
 use tokio::net::TcpStream;
 
-use super::headers::{HeaderIter, HttpHeader};
+use super::body::BodyReader;
+use super::body_decoder::{BodyDecoder, State};
+use super::framing::{response_framing, Strictness};
+use super::header_map::HeaderMap;
+use super::headers::{check_field, trim_ows, HeaderIter, HttpHeader};
+use super::limits::{LimitError, Limits};
+use super::media_type::MediaType;
 use super::skip_line;
 use super::status_line::Status;
 
@@ -14,60 +18,197 @@ pub struct Context<S: Socket = TcpStream> {
     url: url::Url,
     buffer: Buffer<S>,
     response_meta: Vec<u8>,
-    state: RefCell<State>,
+    response_headers: HeaderMap,
+    decoder: BodyDecoder,
+    forward_proxy: Option<Proxy>,
+    host_sent: bool,
+    request_method: Method,
+    strictness: Strictness,
+    limits: Limits,
+    unusable: bool,
 }
 
 impl Context {
+    /// Connects to the URL host, through the proxy from the environment if there is one.
     pub async fn new(url: impl AsRef<str>) -> anyhow::Result<Self> {
         let url = url::Url::parse(url.as_ref()).context("parse URL")?;
-        let buffer = Buffer::new(
-            TcpStream::connect((
-                url.host_str()
-                    .ok_or_else(|| anyhow::Error::msg("URL has no host"))?,
-                url.port_or_known_default().unwrap_or(80),
-            ))
-            .await
-            .context("establish connection to remote host")?,
-        );
-        Ok(Self {
+        match Proxy::from_env(&url)? {
+            Some(proxy) => Self::with_connector(url, &proxy).await,
+            None => Self::with_connector(url, &TcpConnector::default()).await,
+        }
+    }
+
+    pub async fn with_proxy(url: impl AsRef<str>, proxy: Proxy) -> anyhow::Result<Self> {
+        Self::with_connector(url, &proxy).await
+    }
+}
+
+impl<S: Socket> Context<S> {
+    pub async fn with_connector<C>(url: impl AsRef<str>, connector: &C) -> anyhow::Result<Self>
+    where
+        C: Connector<Socket = S>,
+    {
+        let url = url::Url::parse(url.as_ref()).context("parse URL")?;
+        let socket = connector.connect(&url).await?;
+        let mut context = Self::from_socket(url.clone(), socket);
+        context.forward_proxy = connector.forward_proxy(&url).cloned();
+        Ok(context)
+    }
+
+    /// The connection and the bytes read ahead after the response head.
+    pub(crate) fn into_socket_parts(self) -> (S, Vec<u8>) {
+        self.buffer.into_parts()
+    }
+
+    /// Releases the connection after a 101 response or a successful CONNECT.
+    ///
+    /// Bytes already read ahead from the socket are returned first by the [`Rewind`].
+    /// Fails while the response body is unread.
+    pub fn into_parts(self) -> anyhow::Result<Rewind<S>> {
+        if self.has_response() {
+            return Err(anyhow::Error::msg(
+                "cannot release connection before the end of the response body",
+            ));
+        }
+        let (socket, read_ahead) = self.into_socket_parts();
+        Ok(Rewind::new(socket, read_ahead))
+    }
+
+    /// Wraps an already established connection to the URL host.
+    pub fn from_socket(url: url::Url, socket: S) -> Self {
+        Self {
             url,
-            buffer,
+            buffer: Buffer::new(socket),
             response_meta: vec![],
-            state: RefCell::new(State::SendingRequest),
-        })
+            response_headers: HeaderMap::new(),
+            decoder: BodyDecoder::default(),
+            forward_proxy: None,
+            host_sent: false,
+            request_method: Method::Get,
+            strictness: Strictness::default(),
+            limits: Limits::default(),
+            unusable: false,
+        }
     }
 
-    pub fn host(&self) -> String {
-        self.url.host().unwrap().to_string()
+    /// Exceeding any of the limits fails with [`LimitError`].
+    pub fn set_limits(&mut self, limits: Limits) {
+        self.limits = limits;
     }
 
-    pub fn host_header(&self) -> HttpHeader {
-        HttpHeader::Host(self.url.host().unwrap().to_owned())
+    pub fn limits(&self) -> Limits {
+        self.limits
+    }
+
+    /// Strict by default: ambiguous response framing fails with [`super::framing::FramingError`].
+    pub fn set_strictness(&mut self, strictness: Strictness) {
+        self.strictness = strictness;
+    }
+
+    pub fn url(&self) -> &url::Url {
+        &self.url
+    }
+
+    /// Fails for a URL without a host, which `from_socket` accepts.
+    pub fn host(&self) -> anyhow::Result<String> {
+        Ok(self.url_host()?.to_string())
+    }
+
+    /// The port is omitted when it is the default one of the URL scheme.
+    pub fn host_header(&self) -> anyhow::Result<HttpHeader> {
+        Ok(HttpHeader::Host {
+            host: self.url_host()?.to_owned(),
+            port: self.url.port(),
+        })
+    }
+
+    fn url_host(&self) -> anyhow::Result<url::Host<&str>> {
+        self.url
+            .host()
+            .ok_or_else(|| anyhow::Error::msg("URL has no host"))
     }
-}
 
-impl<S: Socket> Context<S> {
     pub fn begin(&mut self) {}
 
     pub fn end(&mut self) {}
 
     pub async fn begin_request(&mut self, method: Method) -> anyhow::Result<()> {
-        let resource = self.url.path();
-        let msg = format!("{} {} HTTP/1.1\r\n", method.as_ref(), resource);
+        if self.unusable {
+            return Err(anyhow::Error::msg(
+                "connection is out of sync after a failed request body",
+            ));
+        }
+        self.request_method = method;
+        let mut msg = format!("{} {} HTTP/1.1\r\n", method.as_ref(), self.request_target());
+        if let Some(header) = self.forward_proxy.as_ref().and_then(Proxy::authorization) {
+            msg.push_str(&format!("{}\r\n", header));
+        }
+        self.host_sent = false;
         self.buffer.write_str(&msg).await.context("send start line")
     }
 
+    /// Points the next request at another resource of the same host.
+    pub fn set_resource(&mut self, path: &str, query: Option<&str>) {
+        self.url.set_path(path);
+        self.url.set_query(query);
+    }
+
+    /// Authority-form for CONNECT, absolute-form when talking to a forward proxy,
+    /// origin-form otherwise.
+    pub fn request_target(&self) -> String {
+        if self.request_method == Method::Connect {
+            let host = self.url.host_str().unwrap_or_default();
+            let port = self.url.port_or_known_default().unwrap_or(80);
+            crate::proxy::authority(host, port)
+        } else if self.forward_proxy.is_some() {
+            let mut url = self.url.clone();
+            url.set_fragment(None);
+            url.to_string()
+        } else {
+            match self.url.query() {
+                Some(query) => format!("{}?{}", self.url.path(), query),
+                None => self.url.path().to_owned(),
+            }
+        }
+    }
+
     pub fn end_request(&mut self) {}
 
+    /// The request body did not match its framing, no other request may follow.
+    pub(crate) fn set_unusable(&mut self) {
+        self.unusable = true;
+    }
+
     pub async fn request_header(&mut self, header: HttpHeader) -> anyhow::Result<()> {
-        let msg = format!("{}\r\n", header.to_string());
+        if let HttpHeader::Host { .. } = header {
+            self.host_sent = true;
+        }
+        let field = header.to_string();
+        let (name, value) = field.split_once(':').unwrap_or((&field, ""));
+        check_field(name, trim_ows(value).as_bytes())?;
         self.buffer
-            .write_str(&msg)
+            .write_str(&format!("{}\r\n", field))
             .await
             .context("send request header")
     }
 
+    /// Sends all the headers with a single write.
+    pub async fn request_headers(&mut self, headers: &HeaderMap) -> anyhow::Result<()> {
+        let bytes = headers.to_bytes()?;
+        if headers.contains("host") {
+            self.host_sent = true;
+        }
+        self.buffer
+            .write_bytes(bytes)
+            .await
+            .context("send request headers")
+    }
+
+    /// Sends the Host header first if the caller has not sent one.
     pub async fn request_headers_end(&mut self) -> anyhow::Result<()> {
+        if !self.host_sent {
+            self.request_header(self.host_header()?).await?;
+        }
         self.buffer
             .write_str("\r\n")
             .await
@@ -80,122 +221,127 @@ impl<S: Socket> Context<S> {
             .await
             .context("send request body chunk")
     }
+
+    /// Sends one chunk of a `Transfer-Encoding: chunked` request body, empty data is skipped.
+    pub async fn request_chunk(&mut self, data: impl AsRef<[u8]>) -> anyhow::Result<()> {
+        let data = data.as_ref();
+        if data.is_empty() {
+            return Ok(());
+        }
+        let mut chunk = format!("{:x}\r\n", data.len()).into_bytes();
+        chunk.extend_from_slice(data);
+        chunk.extend_from_slice(b"\r\n");
+        self.buffer
+            .write_bytes(chunk)
+            .await
+            .context("send request chunk")
+    }
+
+    /// Sends the last chunk of a chunked request body without trailers.
+    pub async fn request_chunks_end(&mut self) -> anyhow::Result<()> {
+        self.buffer
+            .write_str("0\r\n\r\n")
+            .await
+            .context("send last request chunk")
+    }
 }
 
 impl<S: Socket> Context<S> {
     pub async fn response_begin(&mut self) -> anyhow::Result<()> {
-        self.response_meta = self.buffer.read_until_and_chop(b"\r\n\r\n").await?;
-
-        for header in self.response_header_iter() {
-            if let HttpHeader::ContentLength(content_length) = header {
-                if let State::Chunked {
-                    chunk_size: _,
-                    bytes_read: _,
-                } = self.state()
-                {
-                    // ignore content-length header according https://datatracker.ietf.org/doc/html/rfc9112#name-message-body-length
-                    // refer to the clause 3.
-                } else {
-                    self.state.replace(State::Content {
-                        content_length,
-                        bytes_read: 0,
-                    });
-                }
-            }
-            if let HttpHeader::TransferEncodingChunked = header {
-                self.state.replace(State::Chunked {
-                    chunk_size: usize::MAX,
-                    bytes_read: usize::MAX,
-                });
-            }
-        }
+        self.response_meta = self
+            .buffer
+            .read_until_and_chop(
+                b"\r\n\r\n",
+                self.limits.max_header_section,
+                LimitError::HeaderSectionTooLarge,
+            )
+            .await?;
+        self.limits.check_head(&self.response_meta)?;
+
+        let headers = HeaderMap::parse_fields(skip_line(&self.response_meta), self.strictness)?;
+        let framing = response_framing(
+            self.request_method,
+            self.status()?.code,
+            headers.str_fields(),
+            self.strictness,
+        )?;
+        self.response_headers = headers;
+        self.decoder = BodyDecoder::new(framing, &self.limits)?;
         Ok(())
     }
 
-    pub fn status(&self) -> anyhow::Result<Status> {
+    pub fn status(&self) -> anyhow::Result<Status<'_>> {
         Status::new(&self.response_meta)
     }
 
     pub fn state(&self) -> State {
-        *self.state.borrow()
+        self.decoder.state()
     }
 
-    pub fn response_header_iter(&self) -> HeaderIter {
-        HeaderIter::new(skip_line(&self.response_meta))
+    pub fn response_header_iter(&self) -> HeaderIter<'_> {
+        HeaderIter::new(&self.response_headers)
+    }
+
+    pub fn body(&mut self) -> BodyReader<'_, S> {
+        BodyReader::new(self)
+    }
+
+    pub fn response_headers(&self) -> &HeaderMap {
+        &self.response_headers
+    }
+
+    /// Trailer fields that came after the last chunk.
+    pub fn response_trailer_iter(&self) -> HeaderIter<'_> {
+        HeaderIter::new(self.decoder.trailers())
     }
 
     pub async fn response_body_chunk_read(&mut self, buf: &mut [u8]) -> anyhow::Result<usize> {
-        match self.state() {
-            State::SendingRequest => Err(anyhow::Error::msg(
-                "unexpected read call while sending request",
-            )),
-            State::Content {
-                content_length: _,
-                bytes_read: _,
-            } => {
-                let n = self.get_chunk(buf).await?;
-                if self.bytes_wait()? == 0 {
-                    self.state.replace(State::Exhausted);
-                }
-                Ok(n)
+        self.decoder
+            .read(&mut self.buffer, buf, &self.limits, self.strictness)
+            .await
+    }
+
+    /// Reads the rest of the response body, at most `Limits::max_buffered_body` bytes.
+    pub async fn bytes(&mut self) -> anyhow::Result<Vec<u8>> {
+        let limit = self.limits.max_buffered_body;
+        if self.content_length().is_ok_and(|length| length > limit) {
+            return Err(LimitError::BodyTooLarge.into());
+        }
+        let mut body = vec![];
+        let mut buf = [0; 4096];
+        let mut reader = self.body();
+        loop {
+            let n = reader.read(&mut buf).await?;
+            if n == 0 {
+                return Ok(body);
             }
-            State::Chunked {
-                chunk_size: _,
-                bytes_read: _,
-            } => {
-                if self.bytes_wait()? == 0 {
-                    self.start_chunk().await?;
-                }
-                let n = if self.bytes_wait().is_ok() {
-                    self.get_chunk(buf).await?
-                } else {
-                    return Ok(0);
-                };
-                if self.bytes_wait()? == 0 {
-                    self.end_chunk().await?;
-                }
-                Ok(n)
+            if body.len() + n > limit {
+                return Err(LimitError::BodyTooLarge.into());
             }
-            State::Exhausted => Err(anyhow::Error::msg("context exhausted")),
+            body.extend_from_slice(&buf[..n]);
         }
     }
 
-    async fn get_chunk(&mut self, buf: &mut [u8]) -> anyhow::Result<usize> {
-        let need = buf.len().min(self.bytes_wait()?);
-        let result = self.buffer.read_some_bytes(&mut buf[..need]).await?;
-        self.reduce_bytes(result)?;
-        Ok(result)
-    }
-
     pub fn response_end(&mut self) {}
 }
 
 impl<S: Socket> Context<S> {
     pub fn has_response(&self) -> bool {
-        match self.state() {
-            State::SendingRequest => false,
-            State::Content {
-                content_length,
-                bytes_read,
-            } => bytes_read < content_length,
-            State::Chunked {
-                chunk_size: _,
-                bytes_read: _,
-            } => true,
-            State::Exhausted => false,
-        }
+        self.decoder.has_body()
     }
 
     pub fn content_length(&self) -> anyhow::Result<usize> {
-        if let State::Content {
-            content_length,
-            bytes_read: _,
-        } = self.state()
-        {
-            Ok(content_length)
-        } else {
-            Err(anyhow::Error::msg("no content length"))
-        }
+        self.decoder
+            .content_length()
+            .ok_or_else(|| anyhow::Error::msg("no content length"))
+    }
+
+    /// Content-Type of the response, `None` when absent.
+    pub fn response_media_type(&self) -> anyhow::Result<Option<MediaType>> {
+        self.response_headers
+            .get_str("content-type")
+            .map(|value| value.parse().context("parse content type"))
+            .transpose()
     }
 
     pub fn debug(&self) -> String {
@@ -204,75 +350,102 @@ impl<S: Socket> Context<S> {
             std::str::from_utf8(self.buffer.buffer())
         )
     }
+}
 
-    fn bytes_wait(&self) -> anyhow::Result<usize> {
-        match self.state() {
-            State::Content {
-                content_length,
-                bytes_read,
-            } => Ok(content_length - bytes_read),
-            State::Chunked {
-                chunk_size,
-                bytes_read,
-            } => Ok(chunk_size - bytes_read),
-            _ => Err(anyhow::Error::msg("ask for bytes read")),
-        }
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::http::framing::TruncatedBody;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    async fn closed_after(response: &[u8]) -> Context<tokio::io::DuplexStream> {
+        let (client, mut server) = tokio::io::duplex(4096);
+        server.write_all(response).await.unwrap();
+        drop(server);
+        let url = url::Url::parse("http://example.org/").unwrap();
+        let mut http = Context::from_socket(url, client);
+        http.response_begin().await.unwrap();
+        http
     }
 
-    fn reduce_bytes(&mut self, bytes: usize) -> anyhow::Result<()> {
-        match self.state.borrow_mut().deref_mut() {
-            State::Content {
-                content_length: _,
-                bytes_read,
-            }
-            | State::Chunked {
-                chunk_size: _,
-                bytes_read,
-            } => bytes_read.add_assign(bytes),
-            _ => return Err(anyhow::Error::msg("reduce bytes")),
-        }
-        Ok(())
+    #[tokio::test]
+    async fn try_truncated_body() {
+        let mut http = closed_after(b"HTTP/1.1 200 OK\r\nContent-Length: 10\r\n\r\nhello").await;
+        let e = http.bytes().await.unwrap_err();
+        assert_eq!(
+            Some(&TruncatedBody {
+                received: 5,
+                expected: Some(10)
+            }),
+            e.downcast_ref()
+        );
+
+        let mut http = closed_after(
+            b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n2\r\nhe\r\n8\r\nllo",
+        )
+        .await;
+        let e = http.bytes().await.unwrap_err();
+        assert_eq!(
+            Some(&TruncatedBody {
+                received: 5,
+                expected: None
+            }),
+            e.downcast_ref()
+        );
+
+        let mut http = closed_after(b"HTTP/1.1 200 OK\r\nContent-Length: 5\r\n\r\nhello").await;
+        assert_eq!(b"hello", http.bytes().await.unwrap().as_slice());
+
+        let mut http = closed_after(b"HTTP/1.1 200 OK\r\nContent-Length: 0\r\n\r\n").await;
+        let mut buf = [0; 8];
+        assert_eq!(0, http.response_body_chunk_read(&mut buf).await.unwrap());
+        assert_eq!(State::Exhausted, http.state());
     }
 
-    async fn start_chunk(&mut self) -> anyhow::Result<()> {
-        let chunk_size_line = self
-            .buffer
-            .read_line()
-            .await
-            .context("read chunk header from socket")?;
-        let chunk_size_str =
-            std::str::from_utf8(&chunk_size_line).context("chunk header contains non-UTF8")?;
-        let chunk_size =
-            usize::from_str_radix(chunk_size_str, 16).context("chunk header is not hexadecimal")?;
-        if chunk_size == 0 {
-            self.state.replace(State::Exhausted);
-        } else {
-            self.state.replace(State::Chunked {
-                chunk_size,
-                bytes_read: 0,
-            });
-        }
-        Ok(())
+    #[tokio::test]
+    async fn try_unparsable_value_in_strict_mode() {
+        let mut http =
+            closed_after(b"HTTP/1.1 200 OK\r\nDate: yesterday\r\nContent-Length: 2\r\n\r\nok")
+                .await;
+        assert_eq!(
+            vec![
+                HttpHeader::Custom {
+                    name: "Date".to_owned(),
+                    value: "yesterday".to_owned(),
+                },
+                HttpHeader::ContentLength(2),
+            ],
+            http.response_header_iter().collect::<Vec<_>>()
+        );
+        assert_eq!(b"ok", http.bytes().await.unwrap().as_slice());
     }
 
-    async fn end_chunk(&mut self) -> anyhow::Result<()> {
-        self.buffer.read_line().await?;
-        Ok(())
+    #[tokio::test]
+    async fn try_url_without_host() {
+        let url = url::Url::parse("unix:/run/app.sock").unwrap();
+        let mut http = Context::from_socket(url, crate::test_util::MockSocket::new());
+        http.begin_request(Method::Get).await.unwrap();
+        assert!(http.request_headers_end().await.is_err());
     }
-}
 
-mod context_state {
-    #[derive(Debug, Clone, Copy, PartialEq)]
-    pub enum State {
-        SendingRequest,
-        Content {
-            content_length: usize,
-            bytes_read: usize,
-        },
-        Chunked {
-            chunk_size: usize,
-            bytes_read: usize,
-        },
-        Exhausted,
+    #[tokio::test]
+    async fn try_into_parts() {
+        let (client, mut server) = tokio::io::duplex(4096);
+        server
+            .write_all(b"HTTP/1.1 101 Switching Protocols\r\nUpgrade: custom\r\n\r\nearly")
+            .await
+            .unwrap();
+        let url = url::Url::parse("http://example.org/").unwrap();
+        let mut http = Context::from_socket(url, client);
+        http.response_begin().await.unwrap();
+        let mut socket = http.into_parts().unwrap();
+        server.write_all(b" late").await.unwrap();
+        drop(server);
+        let mut received = String::new();
+        socket.read_to_string(&mut received).await.unwrap();
+        assert_eq!("early late", received);
+
+        let http = closed_after(b"HTTP/1.1 200 OK\r\nContent-Length: 5\r\n\r\nhello").await;
+        assert!(http.into_parts().is_err());
     }
 }
@@ -0,0 +1,129 @@
+//! `application/x-www-form-urlencoded` bodies.
+
+use super::{Context, MediaType};
+use crate::{HttpHeader, Socket};
+use std::fmt;
+
+/// Ordered name/value pairs, a name may repeat.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Form {
+    fields: Vec<(String, String)>,
+}
+
+impl Form {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Decodes `a=1&b=x+y`, `+` and percent-escapes included.
+    pub fn parse(body: &[u8]) -> Self {
+        url::form_urlencoded::parse(body).into_owned().collect()
+    }
+
+    pub fn with_field(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.append(name, value);
+        self
+    }
+
+    pub fn append(&mut self, name: impl Into<String>, value: impl Into<String>) {
+        self.fields.push((name.into(), value.into()));
+    }
+
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.fields
+            .iter()
+            .find(|(n, _)| n == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    pub fn get_all<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a str> + 'a {
+        self.iter()
+            .filter(move |(n, _)| *n == name)
+            .map(|(_, value)| value)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.fields
+            .iter()
+            .map(|(name, value)| (name.as_str(), value.as_str()))
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.fields.is_empty()
+    }
+}
+
+impl<N: Into<String>, V: Into<String>> FromIterator<(N, V)> for Form {
+    fn from_iter<T: IntoIterator<Item = (N, V)>>(iter: T) -> Self {
+        Self {
+            fields: iter
+                .into_iter()
+                .map(|(name, value)| (name.into(), value.into()))
+                .collect(),
+        }
+    }
+}
+
+/// Percent-encoded body, e.g. `q=a+b&lang=ru`.
+impl fmt::Display for Form {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(
+            &url::form_urlencoded::Serializer::new(String::new())
+                .extend_pairs(&self.fields)
+                .finish(),
+        )
+    }
+}
+
+impl<S: Socket> Context<S> {
+    /// Sends Content-Type and Content-Length, ends the headers and sends the encoded form.
+    pub async fn request_form(&mut self, form: &Form) -> anyhow::Result<()> {
+        let body = form.to_string();
+        self.request_header(HttpHeader::ContentType(
+            MediaType::application_www_form_urlencoded(),
+        ))
+        .await?;
+        self.request_header(HttpHeader::ContentLength(body.len()))
+            .await?;
+        self.request_headers_end().await?;
+        self.request_body_chunk(body).await
+    }
+
+    /// Reads the rest of the response body like [`Context::bytes`] and decodes it as a form.
+    pub async fn form(&mut self) -> anyhow::Result<Form> {
+        Ok(Form::parse(&self.bytes().await?))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    #[tokio::test]
+    async fn try_form() {
+        let (client, mut server) = tokio::io::duplex(4096);
+        server
+            .write_all(
+                b"HTTP/1.1 200 OK\r\nContent-Length: 31\r\n\r\nstatus=ok&id=7&msg=%D0%B4%D0%B0",
+            )
+            .await
+            .unwrap();
+        let url = url::Url::parse("http://example.org/submit").unwrap();
+        let mut http = Context::from_socket(url, client);
+        http.begin_request(crate::Method::Post).await.unwrap();
+        let form = Form::new()
+            .with_field("q", "a b&c")
+            .with_field("lang", "ru");
+        http.request_form(&form).await.unwrap();
+        http.response_begin().await.unwrap();
+        let response = http.form().await.unwrap();
+        assert_eq!(Some("ok"), response.get("status"));
+        assert_eq!(Some("да"), response.get("msg"));
+
+        let expected = "POST /submit HTTP/1.1\r\nContent-Type: application/x-www-form-urlencoded\r\nContent-Length: 17\r\nHost: example.org\r\n\r\nq=a+b%26c&lang=ru";
+        let mut request = vec![0; expected.len()];
+        server.read_exact(&mut request).await.unwrap();
+        assert_eq!(expected, String::from_utf8(request).unwrap());
+    }
+}
@@ -0,0 +1,262 @@
+use crate::Method;
+
+/// How the end of a response body is found, see https://datatracker.ietf.org/doc/html/rfc9112#name-message-body-length
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Framing {
+    NoBody,
+    Chunked,
+    ContentLength(usize),
+    UntilClose,
+}
+
+/// `Lenient` accepts ambiguous framing of legacy servers the way most browsers do.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum Strictness {
+    #[default]
+    Strict,
+    Lenient,
+}
+
+/// Framing that a proxy could interpret differently from the next hop.
+#[derive(Debug, Clone, PartialEq)]
+pub enum FramingError {
+    ContentLengthWithTransferEncoding,
+    ChunkedNotFinal,
+    ChunkedAppliedTwice,
+    InvalidContentLength(String),
+    ConflictingContentLength,
+}
+
+impl std::fmt::Display for FramingError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::ContentLengthWithTransferEncoding => {
+                f.write_str("both Content-Length and Transfer-Encoding are present")
+            }
+            Self::ChunkedNotFinal => f.write_str("chunked is not the final transfer coding"),
+            Self::ChunkedAppliedTwice => f.write_str("chunked transfer coding is applied twice"),
+            Self::InvalidContentLength(value) => {
+                write!(f, "invalid Content-Length value {:?}", value)
+            }
+            Self::ConflictingContentLength => f.write_str("conflicting Content-Length values"),
+        }
+    }
+}
+
+impl std::error::Error for FramingError {}
+
+/// Connection closed before the end of a body with Content-Length or chunked framing.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TruncatedBody {
+    pub received: usize,
+    /// Content-Length, `None` for a chunked body.
+    pub expected: Option<usize>,
+}
+
+impl std::fmt::Display for TruncatedBody {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.expected {
+            Some(expected) => write!(
+                f,
+                "connection closed after {} of {} body bytes",
+                self.received, expected
+            ),
+            None => write!(
+                f,
+                "connection closed inside a chunk after {} body bytes",
+                self.received
+            ),
+        }
+    }
+}
+
+impl std::error::Error for TruncatedBody {}
+
+/// Applies the rules of RFC 9112 §6.3 to the status code and raw header fields of a response.
+pub fn response_framing<'a>(
+    request_method: Method,
+    status_code: u16,
+    headers: impl IntoIterator<Item = (&'a str, &'a str)>,
+    strictness: Strictness,
+) -> Result<Framing, FramingError> {
+    if request_method == Method::Head
+        || (100..200).contains(&status_code)
+        || status_code == 204
+        || status_code == 304
+        || (request_method == Method::Connect && (200..300).contains(&status_code))
+    {
+        return Ok(Framing::NoBody);
+    }
+    field_framing(headers, strictness, Framing::UntilClose)
+}
+
+/// Applies the rules of RFC 9112 §6.3 to the raw header fields of a request.
+///
+/// A request without Content-Length or chunked coding has no body.
+pub fn request_framing<'a>(
+    headers: impl IntoIterator<Item = (&'a str, &'a str)>,
+    strictness: Strictness,
+) -> Result<Framing, FramingError> {
+    match field_framing(headers, strictness, Framing::NoBody)? {
+        Framing::UntilClose => Err(FramingError::ChunkedNotFinal),
+        framing => Ok(framing),
+    }
+}
+
+/// Framing from Transfer-Encoding and Content-Length, `missing` when neither is present.
+fn field_framing<'a>(
+    headers: impl IntoIterator<Item = (&'a str, &'a str)>,
+    strictness: Strictness,
+    missing: Framing,
+) -> Result<Framing, FramingError> {
+    let mut transfer_codings = vec![];
+    let mut content_lengths = vec![];
+    for (name, value) in headers {
+        if name.eq_ignore_ascii_case("transfer-encoding") {
+            transfer_codings.extend(
+                value
+                    .split(',')
+                    .map(|coding| coding.trim().to_ascii_lowercase())
+                    .filter(|coding| !coding.is_empty()),
+            );
+        } else if name.eq_ignore_ascii_case("content-length") {
+            content_lengths.extend(value.split(',').map(|length| length.trim().to_owned()));
+        }
+    }
+
+    if !transfer_codings.is_empty() {
+        if !content_lengths.is_empty() && strictness == Strictness::Strict {
+            return Err(FramingError::ContentLengthWithTransferEncoding);
+        }
+        let chunked = transfer_codings.iter().filter(|c| *c == "chunked").count();
+        if chunked > 1 && strictness == Strictness::Strict {
+            return Err(FramingError::ChunkedAppliedTwice);
+        }
+        return match transfer_codings.last() {
+            Some(last) if last == "chunked" => Ok(Framing::Chunked),
+            _ if strictness == Strictness::Strict => Err(FramingError::ChunkedNotFinal),
+            _ => Ok(Framing::UntilClose),
+        };
+    }
+
+    let mut framing = None;
+    for value in content_lengths {
+        if value.is_empty() || !value.bytes().all(|b| b.is_ascii_digit()) {
+            return Err(FramingError::InvalidContentLength(value));
+        }
+        let length = value
+            .parse()
+            .map_err(|_| FramingError::InvalidContentLength(value))?;
+        match framing {
+            Some(Framing::ContentLength(previous)) if previous != length => {
+                return Err(FramingError::ConflictingContentLength)
+            }
+            _ => framing = Some(Framing::ContentLength(length)),
+        }
+    }
+    Ok(framing.unwrap_or(missing))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn try_valid_framing() {
+        let framing = |headers: &[(&str, &str)]| {
+            response_framing(
+                Method::Get,
+                200,
+                headers.iter().copied(),
+                Strictness::Strict,
+            )
+        };
+        assert_eq!(Ok(Framing::UntilClose), framing(&[]));
+        assert_eq!(
+            Ok(Framing::ContentLength(5)),
+            framing(&[("Content-Length", "5"), ("content-length", "5, 5")])
+        );
+        assert_eq!(
+            Ok(Framing::Chunked),
+            framing(&[
+                ("Transfer-Encoding", "gzip"),
+                ("Transfer-Encoding", "Chunked")
+            ])
+        );
+        assert_eq!(
+            Ok(Framing::NoBody),
+            response_framing(
+                Method::Get,
+                304,
+                [("Content-Length", "5")],
+                Strictness::Strict
+            )
+        );
+        assert_eq!(
+            Ok(Framing::NoBody),
+            response_framing(
+                Method::Head,
+                200,
+                [("Content-Length", "5")],
+                Strictness::Strict
+            )
+        );
+    }
+
+    #[test]
+    fn try_ambiguous_framing() {
+        let strict = |headers: &[(&str, &str)]| {
+            response_framing(
+                Method::Get,
+                200,
+                headers.iter().copied(),
+                Strictness::Strict,
+            )
+        };
+        let lenient = |headers: &[(&str, &str)]| {
+            response_framing(
+                Method::Get,
+                200,
+                headers.iter().copied(),
+                Strictness::Lenient,
+            )
+        };
+
+        let both = [("Content-Length", "5"), ("Transfer-Encoding", "chunked")];
+        assert_eq!(
+            Err(FramingError::ContentLengthWithTransferEncoding),
+            strict(&both)
+        );
+        assert_eq!(Ok(Framing::Chunked), lenient(&both));
+
+        let not_final = [("Transfer-Encoding", "chunked, gzip")];
+        assert_eq!(Err(FramingError::ChunkedNotFinal), strict(&not_final));
+        assert_eq!(Ok(Framing::UntilClose), lenient(&not_final));
+
+        let twice = [("Transfer-Encoding", "chunked, chunked")];
+        assert_eq!(Err(FramingError::ChunkedAppliedTwice), strict(&twice));
+
+        let conflicting = [("Content-Length", "5"), ("Content-Length", "6")];
+        assert_eq!(
+            Err(FramingError::ConflictingContentLength),
+            lenient(&conflicting)
+        );
+        assert_eq!(
+            Err(FramingError::InvalidContentLength("+5".to_owned())),
+            lenient(&[("Content-Length", "+5")])
+        );
+    }
+
+    #[test]
+    fn try_request_framing() {
+        assert_eq!(Ok(Framing::NoBody), request_framing([], Strictness::Strict));
+        assert_eq!(
+            Ok(Framing::ContentLength(5)),
+            request_framing([("Content-Length", "5")], Strictness::Strict)
+        );
+        assert_eq!(
+            Err(FramingError::ChunkedNotFinal),
+            request_framing([("Transfer-Encoding", "gzip")], Strictness::Lenient)
+        );
+    }
+}
@@ -0,0 +1,273 @@
+use super::framing::Strictness;
+use super::header_values::list;
+use super::headers::{check_field, split_field, trim_ows, trim_ows_bytes, unfold, HttpHeader};
+
+/// Header fields in insertion order with raw values and case-insensitive names.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct HeaderMap {
+    entries: Vec<(String, Vec<u8>)>,
+}
+
+impl HeaderMap {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Parses a header section, obsolete line folding is replaced by a single space.
+    pub fn parse(block: &[u8]) -> Self {
+        let mut map = Self::new();
+        for line in unfold(block) {
+            map.append_line(&line);
+        }
+        map
+    }
+
+    /// Parses a header section checking every field line with [`split_field`].
+    ///
+    /// Lines that `Lenient` mode lets through are kept the way [`HeaderMap::parse`] keeps them.
+    pub(crate) fn parse_fields(block: &[u8], strictness: Strictness) -> anyhow::Result<Self> {
+        let mut map = Self::new();
+        for line in unfold(block) {
+            match split_field(&line, strictness)? {
+                Some((name, value)) => map.push(name, value),
+                None => map.append_line(&line),
+            }
+        }
+        Ok(map)
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Fails when the name is not a token or the value contains CR, LF or NUL.
+    pub fn append(
+        &mut self,
+        name: impl Into<String>,
+        value: impl Into<Vec<u8>>,
+    ) -> anyhow::Result<()> {
+        let (name, value) = (name.into(), value.into());
+        check_field(&name, &value)?;
+        self.push(name, value);
+        Ok(())
+    }
+
+    /// Replaces all values of `name`, the new value takes the place of the first one.
+    pub fn insert(
+        &mut self,
+        name: impl Into<String>,
+        value: impl Into<Vec<u8>>,
+    ) -> anyhow::Result<()> {
+        let (name, value) = (name.into(), value.into());
+        check_field(&name, &value)?;
+        match self.position(&name) {
+            Some(first) => {
+                self.entries[first] = (name.clone(), value);
+                let mut i = 0;
+                self.entries.retain(|(n, _)| {
+                    i += 1;
+                    i - 1 == first || !n.eq_ignore_ascii_case(&name)
+                });
+            }
+            None => self.push(name, value),
+        }
+        Ok(())
+    }
+
+    /// Returns whether there was any value to remove.
+    pub fn remove(&mut self, name: &str) -> bool {
+        let len = self.entries.len();
+        self.entries.retain(|(n, _)| !n.eq_ignore_ascii_case(name));
+        len != self.entries.len()
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.position(name).is_some()
+    }
+
+    /// First value of `name`.
+    pub fn get(&self, name: &str) -> Option<&[u8]> {
+        self.position(name)
+            .map(|position| self.entries[position].1.as_slice())
+    }
+
+    /// First value of `name` if it is valid UTF-8.
+    pub fn get_str(&self, name: &str) -> Option<&str> {
+        self.get(name)
+            .and_then(|value| std::str::from_utf8(value).ok())
+    }
+
+    pub fn get_all<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a [u8]> + 'a {
+        self.entries
+            .iter()
+            .filter(move |(n, _)| n.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_slice())
+    }
+
+    /// Elements of a comma-separated list header merged over all its fields.
+    ///
+    /// Not suitable for `Set-Cookie`, which is not a list and may contain commas.
+    pub fn get_list<'a>(&'a self, name: &'a str) -> Vec<&'a str> {
+        self.get_all(name)
+            .filter_map(|value| std::str::from_utf8(value).ok())
+            .flat_map(list)
+            .collect()
+    }
+
+    /// First field of `name` as a typed header.
+    pub fn typed(&self, name: &str) -> Option<anyhow::Result<HttpHeader>> {
+        self.iter()
+            .find(|(n, _)| n.eq_ignore_ascii_case(name))
+            .map(|(name, value)| {
+                let value = std::str::from_utf8(value)
+                    .map_err(|_| anyhow::Error::msg("header value contains non-UTF8"))?;
+                HttpHeader::from_name_value(name, value)
+            })
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &[u8])> {
+        self.entries
+            .iter()
+            .map(|(name, value)| (name.as_str(), value.as_slice()))
+    }
+
+    /// Header section in wire format without the final empty line.
+    ///
+    /// Fails on a field that could not be sent as is, which a parsed section may hold.
+    pub fn to_bytes(&self) -> anyhow::Result<Vec<u8>> {
+        let mut result = vec![];
+        for (name, value) in self.iter() {
+            check_field(name, value)?;
+            result.extend_from_slice(name.as_bytes());
+            result.extend_from_slice(b": ");
+            result.extend_from_slice(value);
+            result.extend_from_slice(b"\r\n");
+        }
+        Ok(result)
+    }
+
+    /// Fields whose value is valid UTF-8, which always holds for the framing fields of a map
+    /// built by [`HeaderMap::parse_fields`].
+    pub(crate) fn str_fields(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.iter()
+            .filter_map(|(name, value)| Some((name, std::str::from_utf8(value).ok()?)))
+    }
+
+    pub(crate) fn entries(&self) -> &[(String, Vec<u8>)] {
+        &self.entries
+    }
+
+    /// Unchecked, for fields that are already on the wire or known to be valid.
+    pub(crate) fn push(&mut self, name: impl Into<String>, value: impl Into<Vec<u8>>) {
+        self.entries.push((name.into(), value.into()));
+    }
+
+    fn append_line(&mut self, line: &[u8]) {
+        let (name, value) = match line.iter().position(|b| *b == b':') {
+            Some(colon) => (&line[..colon], &line[colon + 1..]),
+            None => (line, &[][..]),
+        };
+        self.push(
+            trim_ows(&String::from_utf8_lossy(name)),
+            trim_ows_bytes(value).to_vec(),
+        );
+    }
+
+    fn position(&self, name: &str) -> Option<usize> {
+        self.entries
+            .iter()
+            .position(|(n, _)| n.eq_ignore_ascii_case(name))
+    }
+}
+
+impl Extend<HttpHeader> for HeaderMap {
+    fn extend<T: IntoIterator<Item = HttpHeader>>(&mut self, iter: T) {
+        for header in iter {
+            let header = header.to_string();
+            let (name, value) = header.split_once(':').unwrap_or((&header, ""));
+            // `Extend` cannot fail, a `Custom` header with a bad field fails in `to_bytes`.
+            self.push(name, value.trim().as_bytes());
+        }
+    }
+}
+
+impl FromIterator<HttpHeader> for HeaderMap {
+    fn from_iter<T: IntoIterator<Item = HttpHeader>>(iter: T) -> Self {
+        let mut map = Self::new();
+        map.extend(iter);
+        map
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn try_lookup() {
+        let mut map = HeaderMap::parse(
+            b"Content-Type: text/html\r\nVary: Accept\r\nvary: Accept-Encoding, \r\nX-Raw: \xff\r\n",
+        );
+        assert_eq!(Some(&b"text/html"[..]), map.get("content-type"));
+        assert!(map.contains("VARY"));
+        assert_eq!(vec!["Accept", "Accept-Encoding"], map.get_list("Vary"));
+        assert_eq!(Some(&b"\xff"[..]), map.get("x-raw"));
+        assert_eq!(None, map.get_str("x-raw"));
+
+        map.insert("VARY", "*").unwrap();
+        assert_eq!(vec!["*"], map.get_list("vary"));
+        assert_eq!(
+            vec!["Content-Type", "VARY", "X-Raw"],
+            map.iter().map(|(name, _)| name).collect::<Vec<_>>()
+        );
+        assert!(map.remove("x-raw"));
+        assert!(!map.remove("x-raw"));
+
+        let map = HeaderMap::parse(br#"Cache-Control: no-cache="Set-Cookie, Vary", max-age=5"#);
+        assert_eq!(
+            vec![r#"no-cache="Set-Cookie, Vary""#, "max-age=5"],
+            map.get_list("cache-control")
+        );
+    }
+
+    #[test]
+    fn try_typed_headers() {
+        let map: HeaderMap = [
+            HttpHeader::ContentLength(5),
+            HttpHeader::TransferEncodingChunked,
+        ]
+        .into_iter()
+        .collect();
+        assert_eq!(
+            b"Content-Length: 5\r\nTransfer-Encoding: chunked\r\n".to_vec(),
+            map.to_bytes().unwrap()
+        );
+        assert_eq!(
+            HttpHeader::ContentLength(5),
+            map.typed("content-length").unwrap().unwrap()
+        );
+    }
+
+    #[test]
+    fn try_reject_bad_fields() {
+        let mut map = HeaderMap::new();
+        assert!(map.append("X-Injected", "a\r\nEvil: 1").is_err());
+        assert!(map.append("X-Nul", "a\0").is_err());
+        assert!(map.insert("Bad Name", "1").is_err());
+        assert!(map.append("", "1").is_err());
+        assert!(map.is_empty());
+        map.append("X-Ok", &b"\xff\t1"[..]).unwrap();
+
+        let map: HeaderMap = [HttpHeader::Custom {
+            name: "X-Custom".to_owned(),
+            value: "a\nb".to_owned(),
+        }]
+        .into_iter()
+        .collect();
+        assert!(map.to_bytes().is_err());
+    }
+}
@@ -0,0 +1,347 @@
+//! Values of the typed [`super::headers::HttpHeader`] variants.
+
+use anyhow::Context;
+use std::fmt;
+use std::str::FromStr;
+
+/// `"xyzzy"` or weak `W/"xyzzy"`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EntityTag {
+    pub weak: bool,
+    pub tag: String,
+}
+
+impl EntityTag {
+    pub fn strong(tag: impl Into<String>) -> Self {
+        Self {
+            weak: false,
+            tag: tag.into(),
+        }
+    }
+
+    pub fn weak(tag: impl Into<String>) -> Self {
+        Self {
+            weak: true,
+            tag: tag.into(),
+        }
+    }
+
+    /// Weak comparison of RFC 9110 §8.8.3.2, as used by If-None-Match.
+    pub fn weak_eq(&self, other: &EntityTag) -> bool {
+        self.tag == other.tag
+    }
+}
+
+impl FromStr for EntityTag {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        let (weak, quoted) = match s.strip_prefix("W/") {
+            Some(quoted) => (true, quoted),
+            None => (false, s),
+        };
+        let tag = quoted
+            .strip_prefix('"')
+            .and_then(|tag| tag.strip_suffix('"'))
+            .filter(|tag| !tag.contains('"'))
+            .ok_or_else(|| anyhow::Error::msg("entity tag is not a quoted string"))?;
+        Ok(Self {
+            weak,
+            tag: tag.to_owned(),
+        })
+    }
+}
+
+impl fmt::Display for EntityTag {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.weak {
+            f.write_str("W/")?;
+        }
+        write!(f, "\"{}\"", self.tag)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum IfNoneMatch {
+    Any,
+    Tags(Vec<EntityTag>),
+}
+
+impl FromStr for IfNoneMatch {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.trim() == "*" {
+            Ok(Self::Any)
+        } else {
+            list(s)
+                .map(EntityTag::from_str)
+                .collect::<anyhow::Result<_>>()
+                .map(Self::Tags)
+        }
+    }
+}
+
+impl fmt::Display for IfNoneMatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Any => f.write_str("*"),
+            Self::Tags(tags) => write_list(f, tags),
+        }
+    }
+}
+
+/// One range of a `Range: bytes=...` header.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ByteRange {
+    /// `first-last`, both inclusive
+    FromTo(u64, u64),
+    /// `first-`
+    From(u64),
+    /// `-suffix_length`
+    Last(u64),
+}
+
+impl FromStr for ByteRange {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (first, last) = s
+            .trim()
+            .split_once('-')
+            .ok_or_else(|| anyhow::Error::msg("byte range has no '-'"))?;
+        match (first, last) {
+            ("", suffix) => Ok(Self::Last(suffix.parse().context("parse suffix length")?)),
+            (first, "") => Ok(Self::From(first.parse().context("parse first byte")?)),
+            (first, last) => {
+                let first = first.parse().context("parse first byte")?;
+                let last = last.parse().context("parse last byte")?;
+                if first > last {
+                    return Err(anyhow::Error::msg("byte range ends before it starts"));
+                }
+                Ok(Self::FromTo(first, last))
+            }
+        }
+    }
+}
+
+impl fmt::Display for ByteRange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::FromTo(first, last) => write!(f, "{}-{}", first, last),
+            Self::From(first) => write!(f, "{}-", first),
+            Self::Last(suffix) => write!(f, "-{}", suffix),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Range {
+    pub unit: String,
+    pub ranges: Vec<ByteRange>,
+}
+
+impl Range {
+    pub fn bytes(ranges: impl IntoIterator<Item = ByteRange>) -> Self {
+        Self {
+            unit: "bytes".to_owned(),
+            ranges: ranges.into_iter().collect(),
+        }
+    }
+}
+
+impl FromStr for Range {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (unit, ranges) = s
+            .trim()
+            .split_once('=')
+            .ok_or_else(|| anyhow::Error::msg("range has no unit"))?;
+        Ok(Self {
+            unit: unit.trim().to_owned(),
+            ranges: list(ranges)
+                .map(ByteRange::from_str)
+                .collect::<anyhow::Result<_>>()?,
+        })
+    }
+}
+
+impl fmt::Display for Range {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}=", self.unit)?;
+        let ranges: Vec<_> = self.ranges.iter().map(ToString::to_string).collect();
+        f.write_str(&ranges.join(","))
+    }
+}
+
+/// `bytes 0-499/1234`, `bytes 0-499/*` or `bytes */1234`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ContentRange {
+    pub unit: String,
+    pub range: Option<(u64, u64)>,
+    pub complete_length: Option<u64>,
+}
+
+impl FromStr for ContentRange {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (unit, rest) = s
+            .trim()
+            .split_once(' ')
+            .ok_or_else(|| anyhow::Error::msg("content range has no unit"))?;
+        let (range, complete_length) = rest
+            .trim()
+            .split_once('/')
+            .ok_or_else(|| anyhow::Error::msg("content range has no complete length"))?;
+        let range = match range {
+            "*" => None,
+            range => match ByteRange::from_str(range)? {
+                ByteRange::FromTo(first, last) => Some((first, last)),
+                _ => return Err(anyhow::Error::msg("content range is not closed")),
+            },
+        };
+        let complete_length = match complete_length {
+            "*" => None,
+            length => Some(length.parse().context("parse complete length")?),
+        };
+        if range.is_none() && complete_length.is_none() {
+            return Err(anyhow::Error::msg("content range is empty"));
+        }
+        Ok(Self {
+            unit: unit.to_owned(),
+            range,
+            complete_length,
+        })
+    }
+}
+
+impl fmt::Display for ContentRange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} ", self.unit)?;
+        match self.range {
+            Some((first, last)) => write!(f, "{}-{}/", first, last)?,
+            None => f.write_str("*/")?,
+        }
+        match self.complete_length {
+            Some(length) => write!(f, "{}", length),
+            None => f.write_str("*"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RetryAfter {
+    Seconds(u64),
+    Date(httpdate::HttpDate),
+}
+
+impl FromStr for RetryAfter {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        if s.bytes().all(|b| b.is_ascii_digit()) {
+            Ok(Self::Seconds(s.parse().context("parse delay seconds")?))
+        } else {
+            Ok(Self::Date(s.parse().context("parse retry date")?))
+        }
+    }
+}
+
+impl fmt::Display for RetryAfter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Seconds(seconds) => write!(f, "{}", seconds),
+            Self::Date(date) => write!(f, "{}", date),
+        }
+    }
+}
+
+/// `scheme credentials`, e.g. `Bearer mF_9.B5f-4.1JqM`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Credentials {
+    pub scheme: String,
+    pub credentials: String,
+}
+
+impl Credentials {
+    pub fn bearer(token: impl Into<String>) -> Self {
+        Self {
+            scheme: "Bearer".to_owned(),
+            credentials: token.into(),
+        }
+    }
+
+    pub fn basic(username: &str, password: &str) -> Self {
+        use base64::Engine;
+        Self {
+            scheme: "Basic".to_owned(),
+            credentials: base64::engine::general_purpose::STANDARD
+                .encode(format!("{}:{}", username, password)),
+        }
+    }
+}
+
+impl FromStr for Credentials {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        let (scheme, credentials) = s.split_once(' ').unwrap_or((s, ""));
+        if scheme.is_empty() {
+            return Err(anyhow::Error::msg("authorization has no scheme"));
+        }
+        Ok(Self {
+            scheme: scheme.to_owned(),
+            credentials: credentials.trim().to_owned(),
+        })
+    }
+}
+
+impl fmt::Display for Credentials {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.credentials.is_empty() {
+            f.write_str(&self.scheme)
+        } else {
+            write!(f, "{} {}", self.scheme, self.credentials)
+        }
+    }
+}
+
+/// Non-empty elements of a comma-separated list, commas inside quoted strings included.
+pub(crate) fn list(value: &str) -> impl Iterator<Item = &str> {
+    let mut rest = Some(value);
+    std::iter::from_fn(move || {
+        let s = rest?;
+        let (mut quoted, mut escaped) = (false, false);
+        for (i, b) in s.bytes().enumerate() {
+            match b {
+                _ if escaped => escaped = false,
+                b'\\' if quoted => escaped = true,
+                b'"' => quoted = !quoted,
+                b',' if !quoted => {
+                    rest = Some(&s[i + 1..]);
+                    return Some(&s[..i]);
+                }
+                _ => {}
+            }
+        }
+        rest = None;
+        Some(s)
+    })
+    .map(str::trim)
+    .filter(|element| !element.is_empty())
+}
+
+pub(crate) fn write_list<T: fmt::Display>(f: &mut fmt::Formatter<'_>, items: &[T]) -> fmt::Result {
+    for (i, item) in items.iter().enumerate() {
+        if i > 0 {
+            f.write_str(", ")?;
+        }
+        write!(f, "{}", item)?;
+    }
+    Ok(())
+}
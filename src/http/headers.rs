@@ -1,80 +1,319 @@
 use anyhow::Context;
 
+use super::framing::Strictness;
+use super::header_map::HeaderMap;
+use super::header_values::{
+    list, write_list, ContentRange, Credentials, EntityTag, IfNoneMatch, Range, RetryAfter,
+};
+use super::media_type::MediaType;
 use super::{get_line, skip_line};
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum HttpHeader {
     Custom { name: String, value: String },
+    Accept(Vec<String>),
+    AcceptEncoding(Vec<String>),
+    Authorization(Credentials),
+    CacheControl(Vec<String>),
+    Connection(Vec<String>),
+    ContentEncoding(Vec<String>),
     ContentLength(usize),
-    ContentType { media_type: String },
+    ContentRange(ContentRange),
+    ContentType(MediaType),
     Date(httpdate::HttpDate),
-    Host(url::Host),
+    ETag(EntityTag),
+    Expect(String),
+    Host { host: url::Host, port: Option<u16> },
+    IfModifiedSince(httpdate::HttpDate),
+    IfNoneMatch(IfNoneMatch),
+    LastModified(httpdate::HttpDate),
+    Location(String),
+    Range(Range),
+    RetryAfter(RetryAfter),
+    Te(Vec<String>),
+    Trailer(Vec<String>),
     TransferEncodingChunked,
+    Upgrade(Vec<String>),
+    UserAgent(String),
 }
 
 impl HttpHeader {
+    /// Typed header, `Custom` when the value does not parse.
+    pub(crate) fn from_field(name: &str, value: &[u8]) -> Self {
+        let value = String::from_utf8_lossy(value);
+        Self::from_name_value(name, &value).unwrap_or_else(|_| Self::Custom {
+            name: name.to_owned(),
+            value: trim_ows(&value).to_owned(),
+        })
+    }
+
     pub fn from_name_value(name: &str, value: &str) -> anyhow::Result<Self> {
+        let value = trim_ows(value);
         match name.to_lowercase().as_str() {
+            "accept" => Ok(Self::Accept(owned_list(value))),
+            "accept-encoding" => Ok(Self::AcceptEncoding(owned_list(value))),
+            "authorization" => Ok(Self::Authorization(value.parse()?)),
+            "cache-control" => Ok(Self::CacheControl(owned_list(value))),
+            "connection" => Ok(Self::Connection(owned_list(value))),
+            "content-encoding" => Ok(Self::ContentEncoding(owned_list(value))),
             "content-length" => Ok(Self::ContentLength(
-                value.trim().parse().context("parse content length")?,
+                value.parse().context("parse content length")?,
             )),
-            "content-type" => Ok(Self::ContentType {
-                media_type: value.trim().to_owned(),
-            }),
+            "content-range" => Ok(Self::ContentRange(value.parse()?)),
+            "content-type" => Ok(Self::ContentType(value.parse()?)),
             "date" => Ok(Self::Date(value.parse()?)),
-            "host" => Ok(Self::Host(url::Host::parse(value.trim())?)),
+            "etag" => Ok(Self::ETag(value.parse()?)),
+            "expect" => Ok(Self::Expect(value.to_owned())),
+            "host" => host_accept(value),
+            "if-modified-since" => Ok(Self::IfModifiedSince(value.parse()?)),
+            "if-none-match" => Ok(Self::IfNoneMatch(value.parse()?)),
+            "last-modified" => Ok(Self::LastModified(value.parse()?)),
+            "location" => Ok(Self::Location(value.to_owned())),
+            "range" => Ok(Self::Range(value.parse()?)),
+            "retry-after" => Ok(Self::RetryAfter(value.parse()?)),
+            "te" => Ok(Self::Te(owned_list(value))),
+            "trailer" => Ok(Self::Trailer(owned_list(value))),
             "transfer-encoding" => transfer_encoding_accept(value),
+            "upgrade" => Ok(Self::Upgrade(owned_list(value))),
+            "user-agent" => Ok(Self::UserAgent(value.to_owned())),
             _ => Ok(Self::Custom {
                 name: name.to_owned(),
                 value: value.to_owned(),
             }),
         }
     }
+
+    pub fn name(&self) -> &str {
+        match self {
+            Self::Custom { name, value: _ } => name,
+            Self::Accept(_) => "Accept",
+            Self::AcceptEncoding(_) => "Accept-Encoding",
+            Self::Authorization(_) => "Authorization",
+            Self::CacheControl(_) => "Cache-Control",
+            Self::Connection(_) => "Connection",
+            Self::ContentEncoding(_) => "Content-Encoding",
+            Self::ContentLength(_) => "Content-Length",
+            Self::ContentRange(_) => "Content-Range",
+            Self::ContentType(_) => "Content-Type",
+            Self::Date(_) => "Date",
+            Self::ETag(_) => "ETag",
+            Self::Expect(_) => "Expect",
+            Self::Host { host: _, port: _ } => "Host",
+            Self::IfModifiedSince(_) => "If-Modified-Since",
+            Self::IfNoneMatch(_) => "If-None-Match",
+            Self::LastModified(_) => "Last-Modified",
+            Self::Location(_) => "Location",
+            Self::Range(_) => "Range",
+            Self::RetryAfter(_) => "Retry-After",
+            Self::Te(_) => "TE",
+            Self::Trailer(_) => "Trailer",
+            Self::TransferEncodingChunked => "Transfer-Encoding",
+            Self::Upgrade(_) => "Upgrade",
+            Self::UserAgent(_) => "User-Agent",
+        }
+    }
 }
 
-impl ToString for HttpHeader {
-    fn to_string(&self) -> String {
+impl std::fmt::Display for HttpHeader {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: ", self.name())?;
         match self {
-            Self::ContentLength(length) => format!("Content-Length: {}", length),
-            Self::ContentType { media_type } => format!("Content-Type: {}", media_type),
-            Self::Date(date) => format!("Date: {}", date),
-            Self::Host(host) => format!("Host: {}", host),
-            Self::TransferEncodingChunked => "Transfer-Encoding: chunked".to_owned(),
-            Self::Custom { name, value } => format!("{}: {}", name, value),
+            Self::Accept(items)
+            | Self::AcceptEncoding(items)
+            | Self::CacheControl(items)
+            | Self::Connection(items)
+            | Self::ContentEncoding(items)
+            | Self::Te(items)
+            | Self::Trailer(items)
+            | Self::Upgrade(items) => write_list(f, items),
+            Self::Authorization(credentials) => write!(f, "{}", credentials),
+            Self::ContentLength(length) => write!(f, "{}", length),
+            Self::ContentRange(range) => write!(f, "{}", range),
+            Self::ContentType(media_type) => write!(f, "{}", media_type),
+            Self::Date(date) | Self::IfModifiedSince(date) | Self::LastModified(date) => {
+                write!(f, "{}", date)
+            }
+            Self::ETag(tag) => write!(f, "{}", tag),
+            Self::Host {
+                host,
+                port: Some(port),
+            } => write!(f, "{}:{}", host, port),
+            Self::Host { host, port: None } => write!(f, "{}", host),
+            Self::IfNoneMatch(tags) => write!(f, "{}", tags),
+            Self::Range(range) => write!(f, "{}", range),
+            Self::RetryAfter(retry_after) => write!(f, "{}", retry_after),
+            Self::TransferEncodingChunked => f.write_str("chunked"),
+            Self::Expect(value)
+            | Self::Location(value)
+            | Self::UserAgent(value)
+            | Self::Custom { name: _, value } => f.write_str(value),
         }
     }
 }
 
+fn owned_list(value: &str) -> Vec<String> {
+    list(value).map(str::to_owned).collect()
+}
+
+/// Typed view of the fields of a [`HeaderMap`].
 #[derive(Debug)]
 pub struct HeaderIter<'a> {
-    cursor: &'a [u8],
+    inner: std::slice::Iter<'a, (String, Vec<u8>)>,
 }
 
 impl<'a> Iterator for HeaderIter<'a> {
     type Item = HttpHeader;
 
     fn next(&mut self) -> Option<Self::Item> {
-        if self.cursor.is_empty() {
-            return None;
-        }
-        let result = parse_header(self.cursor).ok()?;
-        self.cursor = skip_line(self.cursor);
-        Some(result)
+        self.inner
+            .next()
+            .map(|(name, value)| HttpHeader::from_field(name, value))
     }
 }
 
 impl<'a> HeaderIter<'a> {
-    pub fn new(cursor: &'a [u8]) -> Self {
-        Self { cursor }
+    pub fn new(headers: &'a HeaderMap) -> Self {
+        Self {
+            inner: headers.entries().iter(),
+        }
+    }
+}
+
+/// Parses every field of a header section.
+///
+/// Strictness applies to the field lines only, see [`split_field`]. A value that does not
+/// parse as its typed header is kept as `Custom` in both modes.
+pub fn parse_headers(block: &[u8], strictness: Strictness) -> anyhow::Result<Vec<HttpHeader>> {
+    let map = HeaderMap::parse_fields(block, strictness)?;
+    Ok(HeaderIter::new(&map).collect())
+}
+
+/// Field lines with obsolete line folding replaced by a single space.
+pub(crate) fn unfold(mut cursor: &[u8]) -> Vec<Vec<u8>> {
+    let mut lines: Vec<Vec<u8>> = vec![];
+    while !cursor.is_empty() {
+        let line = get_line(cursor);
+        cursor = skip_line(cursor);
+        match (line.first(), lines.last_mut()) {
+            (Some(b' ' | b'\t'), Some(previous)) => {
+                previous.push(b' ');
+                previous.extend_from_slice(line.trim_ascii_start());
+            }
+            _ if line.is_empty() => {}
+            _ => lines.push(line.to_vec()),
+        }
+    }
+    lines
+}
+
+/// Name and value of a field line, the value without surrounding SP and HTAB.
+///
+/// A line without a colon or with a name that is not a token fails in `Strict` mode.
+/// `Lenient` mode drops whitespace before the colon, like the other header views do,
+/// and returns `None` for a name that is still not a token, so it never names a framing field.
+pub(crate) fn split_field(
+    line: &[u8],
+    strictness: Strictness,
+) -> anyhow::Result<Option<(&str, &str)>> {
+    let malformed = || {
+        anyhow::Error::msg(format!(
+            "malformed header field {:?}",
+            String::from_utf8_lossy(line)
+        ))
+    };
+    let Some(colon) = line.iter().position(|b| *b == b':') else {
+        return match strictness {
+            Strictness::Strict => Err(malformed()),
+            Strictness::Lenient => Ok(None),
+        };
+    };
+    let name = match strictness {
+        Strictness::Strict => &line[..colon],
+        Strictness::Lenient => trim_ows_bytes(&line[..colon]),
+    };
+    let Some(name) = std::str::from_utf8(name).ok().filter(|name| is_token(name)) else {
+        return match strictness {
+            Strictness::Strict => Err(malformed().context("header field name is not a token")),
+            Strictness::Lenient => Ok(None),
+        };
+    };
+    match std::str::from_utf8(trim_ows_bytes(&line[colon + 1..])) {
+        Ok(value) => Ok(Some((name, value))),
+        Err(_) if strictness == Strictness::Lenient && !is_framing_field(name) => Ok(None),
+        Err(_) => Err(malformed().context("header field value contains non-UTF8")),
+    }
+}
+
+fn is_framing_field(name: &str) -> bool {
+    name.eq_ignore_ascii_case("content-length") || name.eq_ignore_ascii_case("transfer-encoding")
+}
+
+/// Strips optional whitespace, which is SP and HTAB only, see RFC 9110 §5.6.3.
+pub(crate) fn trim_ows(s: &str) -> &str {
+    s.trim_matches([' ', '\t'])
+}
+
+pub(crate) fn trim_ows_bytes(mut bytes: &[u8]) -> &[u8] {
+    while let [b' ' | b'\t', rest @ ..] = bytes {
+        bytes = rest;
+    }
+    while let [rest @ .., b' ' | b'\t'] = bytes {
+        bytes = rest;
     }
+    bytes
 }
 
-fn parse_header(line: &[u8]) -> anyhow::Result<HttpHeader> {
-    let (name, value) = std::str::from_utf8(get_line(line))
-        .context("parse header with non-UTF8")?
-        .split_once(':')
-        .ok_or_else(|| anyhow::Error::msg("semicolon ':' not found"))?;
-    HttpHeader::from_name_value(name.trim(), value)
+/// A field that can be sent as is: the name is a token and the value has no CR, LF or NUL.
+pub(crate) fn check_field(name: &str, value: &[u8]) -> anyhow::Result<()> {
+    if !is_token(name) {
+        return Err(anyhow::Error::msg(format!(
+            "header field name {:?} is not a token",
+            name
+        )));
+    }
+    if value.iter().any(|b| matches!(b, b'\r' | b'\n' | b'\0')) {
+        return Err(anyhow::Error::msg(format!(
+            "value of header field {} contains CR, LF or NUL",
+            name
+        )));
+    }
+    Ok(())
+}
+
+pub(crate) fn is_token(s: &str) -> bool {
+    !s.is_empty()
+        && s.bytes()
+            .all(|b| b.is_ascii_alphanumeric() || b"!#$%&'*+-.^_`|~".contains(&b))
+}
+
+/// Accepts `uri-host [ ":" port ]`, IPv6 literals go in brackets.
+fn host_accept(header_value: &str) -> anyhow::Result<HttpHeader> {
+    let (host, port) = match header_value.strip_prefix('[') {
+        Some(bracketed) => {
+            let (ipv6, rest) = bracketed
+                .split_once(']')
+                .ok_or_else(|| anyhow::Error::msg("IPv6 literal has no closing bracket"))?;
+            let port = match rest {
+                "" => None,
+                rest => Some(rest.strip_prefix(':').ok_or_else(|| {
+                    anyhow::Error::msg("IPv6 literal is followed by something other than a port")
+                })?),
+            };
+            (&header_value[..ipv6.len() + 2], port)
+        }
+        None => match header_value.split_once(':') {
+            Some((host, port)) => (host, Some(port)),
+            None => (header_value, None),
+        },
+    };
+    let port = match port {
+        Some(port) if !port.is_empty() => Some(port.parse().context("parse host port")?),
+        _ => None,
+    };
+    Ok(HttpHeader::Host {
+        host: url::Host::parse(host).context("parse host")?,
+        port,
+    })
 }
 
 fn transfer_encoding_accept(header_value: &str) -> anyhow::Result<HttpHeader> {
@@ -94,7 +333,7 @@ mod test {
 
     #[test]
     fn try_content_length() {
-        let h = parse_header(b"Content-Length: 179").unwrap();
+        let h = HttpHeader::from_field("Content-Length", b" 179");
         if let HttpHeader::ContentLength(len) = h {
             assert_eq!(179, len)
         } else {
@@ -102,6 +341,102 @@ mod test {
         }
     }
 
+    #[test]
+    fn try_malformed_headers() {
+        let block =
+            b"Date: yesterday\r\nX-Folded: a\r\n \t b\r\nBad Name: 1\r\nTransfer-Encoding: chunked";
+        assert!(parse_headers(block, Strictness::Strict).is_err());
+        let strict = parse_headers(
+            b"Date: yesterday\r\nX-Folded: a\r\n \t b",
+            Strictness::Strict,
+        )
+        .unwrap();
+        assert_eq!(
+            HttpHeader::Custom {
+                name: "Date".to_owned(),
+                value: "yesterday".to_owned(),
+            },
+            strict[0]
+        );
+        let headers = parse_headers(block, Strictness::Lenient).unwrap();
+        assert_eq!(
+            vec![
+                "Date: yesterday",
+                "X-Folded: a b",
+                "Bad Name: 1",
+                "Transfer-Encoding: chunked"
+            ],
+            headers.iter().map(|h| h.to_string()).collect::<Vec<_>>()
+        );
+        assert_eq!(HttpHeader::TransferEncodingChunked, headers[3]);
+    }
+
+    #[test]
+    fn try_quoted_list() {
+        assert_eq!(
+            HttpHeader::CacheControl(vec![r#"private="a, b""#.to_owned(), "max-age=5".to_owned()]),
+            HttpHeader::from_name_value("Cache-Control", r#"private="a, b", max-age=5"#).unwrap()
+        );
+        let HttpHeader::IfNoneMatch(IfNoneMatch::Tags(tags)) =
+            HttpHeader::from_name_value("If-None-Match", r#""a,b", W/"c""#).unwrap()
+        else {
+            panic!("If-None-Match is not a tag list");
+        };
+        assert_eq!(2, tags.len());
+    }
+
+    #[test]
+    fn try_split_field() {
+        let split = |line: &'static [u8], strictness| split_field(line, strictness);
+        assert_eq!(
+            Some(("Content-Length", "5\x0b")),
+            split(b"Content-Length:\t5\x0b ", Strictness::Strict).unwrap()
+        );
+        assert!(split(b"Transfer-Encoding : chunked", Strictness::Strict).is_err());
+        assert_eq!(
+            Some(("Transfer-Encoding", "chunked")),
+            split(b"Transfer-Encoding : chunked", Strictness::Lenient).unwrap()
+        );
+        assert!(split(b"Bad Name: 1", Strictness::Strict).is_err());
+        assert_eq!(None, split(b"Bad Name: 1", Strictness::Lenient).unwrap());
+        assert!(split(b"Content-Length: \xff", Strictness::Lenient).is_err());
+    }
+
+    #[test]
+    fn try_round_trip_typed_headers() {
+        for line in [
+            "Accept: text/html, application/xhtml+xml;q=0.9, */*;q=0.8",
+            "Accept-Encoding: gzip, br",
+            "Authorization: Bearer mF_9.B5f-4.1JqM",
+            "Cache-Control: no-cache, max-age=60",
+            "Connection: keep-alive",
+            "Content-Encoding: gzip",
+            "Content-Range: bytes 0-499/1234",
+            "Content-Range: bytes */1234",
+            "ETag: W/\"xyzzy\"",
+            "Expect: 100-continue",
+            "If-Modified-Since: Fri, 24 Nov 2023 06:58:19 GMT",
+            "If-None-Match: \"a\", W/\"b\"",
+            "If-None-Match: *",
+            "Last-Modified: Fri, 24 Nov 2023 06:58:19 GMT",
+            "Location: https://example.org/moved",
+            "Range: bytes=0-99,200-,-50",
+            "Retry-After: 120",
+            "Retry-After: Fri, 24 Nov 2023 06:58:19 GMT",
+            "TE: trailers",
+            "Trailer: Expires, Digest",
+            "Upgrade: websocket",
+            "User-Agent: http_chunked/0.1",
+        ] {
+            let (name, value) = line.split_once(':').unwrap();
+            let h = HttpHeader::from_name_value(name, value).unwrap();
+            assert!(!matches!(h, HttpHeader::Custom { .. }), "{}", line);
+            assert_eq!(line, h.to_string());
+        }
+        assert!(HttpHeader::from_name_value("Range", "bytes=9-1").is_err());
+        assert!(HttpHeader::from_name_value("ETag", "xyzzy").is_err());
+    }
+
     #[test]
     fn try_http_date() {
         let h = HttpHeader::from_name_value("Date", " Fri, 24 Nov 2023 06:58:19 GMT").unwrap();
@@ -113,4 +448,21 @@ mod test {
         let h = HttpHeader::from_name_value("host", " test.host.example.org".trim()).unwrap();
         assert_eq!("Host: test.host.example.org", h.to_string());
     }
+
+    #[test]
+    fn try_parse_host_with_port() {
+        for value in [
+            "127.0.0.1:8888",
+            "example.org:8080",
+            "[::1]:8080",
+            "[2001:db8::1]",
+        ] {
+            let h = HttpHeader::from_name_value("Host", value).unwrap();
+            assert_eq!(format!("Host: {}", value), h.to_string());
+        }
+        assert!(HttpHeader::from_name_value("Host", "example.org:http").is_err());
+        assert!(HttpHeader::from_name_value("Host", "[::1").is_err());
+        assert!(HttpHeader::from_name_value("Host", "[::1]junk").is_err());
+        assert!(HttpHeader::from_name_value("Host", "[::1]junk:80").is_err());
+    }
 }
@@ -0,0 +1,270 @@
+//! Conversions to and from the types of the `http` crate.
+
+use super::status_line::Status;
+use super::{BodyReader, Context, HeaderMap, ServerConnection};
+use crate::{Method, Socket};
+use anyhow::Context as AnyHowContext;
+
+impl From<Method> for http::Method {
+    fn from(method: Method) -> Self {
+        match method {
+            Method::Get => http::Method::GET,
+            Method::Head => http::Method::HEAD,
+            Method::Post => http::Method::POST,
+            Method::Put => http::Method::PUT,
+            Method::Delete => http::Method::DELETE,
+            Method::Connect => http::Method::CONNECT,
+            Method::Options => http::Method::OPTIONS,
+            Method::Trace => http::Method::TRACE,
+            Method::Patch => http::Method::PATCH,
+        }
+    }
+}
+
+impl TryFrom<&http::Method> for Method {
+    type Error = anyhow::Error;
+
+    fn try_from(method: &http::Method) -> Result<Self, Self::Error> {
+        method.as_str().parse()
+    }
+}
+
+impl TryFrom<&Status<'_>> for http::StatusCode {
+    type Error = anyhow::Error;
+
+    fn try_from(status: &Status<'_>) -> Result<Self, Self::Error> {
+        http::StatusCode::from_u16(status.code).context("status code out of range")
+    }
+}
+
+impl TryFrom<&HeaderMap> for http::HeaderMap {
+    type Error = anyhow::Error;
+
+    fn try_from(headers: &HeaderMap) -> Result<Self, Self::Error> {
+        let mut result = http::HeaderMap::with_capacity(headers.len());
+        for (name, value) in headers.iter() {
+            result.append(
+                http::HeaderName::from_bytes(name.as_bytes()).context("convert header name")?,
+                http::HeaderValue::from_bytes(value).context("convert header value")?,
+            );
+        }
+        Ok(result)
+    }
+}
+
+impl From<&http::HeaderMap> for HeaderMap {
+    fn from(headers: &http::HeaderMap) -> Self {
+        let mut result = HeaderMap::new();
+        for (name, value) in headers {
+            // Names and values of the `http` crate are valid fields already.
+            result.push(name.as_str(), value.as_bytes());
+        }
+        result
+    }
+}
+
+/// Head of the response read by `response_begin`, the body stays in the context.
+impl<S: Socket> TryFrom<&Context<S>> for http::Response<()> {
+    type Error = anyhow::Error;
+
+    fn try_from(context: &Context<S>) -> Result<Self, Self::Error> {
+        let status = context.status()?;
+        let mut response = http::Response::builder()
+            .status(http::StatusCode::try_from(&status)?)
+            .version(if status.http_version.ends_with("1.0") {
+                http::Version::HTTP_10
+            } else {
+                http::Version::HTTP_11
+            })
+            .body(())
+            .context("build response")?;
+        *response.headers_mut() = http::HeaderMap::try_from(context.response_headers())?;
+        Ok(response)
+    }
+}
+
+/// Head of the request read by `next_request`, the body stays in the connection.
+impl<S: Socket> TryFrom<&ServerConnection<S>> for http::Request<()> {
+    type Error = anyhow::Error;
+
+    fn try_from(connection: &ServerConnection<S>) -> Result<Self, Self::Error> {
+        let mut request = http::Request::builder()
+            .method(http::Method::from(connection.method()))
+            .uri(connection.target())
+            .version(if connection.is_http_1_0() {
+                http::Version::HTTP_10
+            } else {
+                http::Version::HTTP_11
+            })
+            .body(())
+            .context("build request")?;
+        *request.headers_mut() = http::HeaderMap::try_from(connection.request_headers())?;
+        Ok(request)
+    }
+}
+
+impl<S: Socket> Context<S> {
+    /// Sends the request and returns the response with the body still to be read.
+    ///
+    /// The request URI may be in origin-form or point to the host and port of the context.
+    /// With `Transfer-Encoding: chunked` the body goes out as a single chunk.
+    pub async fn send<B: AsRef<[u8]>>(
+        &mut self,
+        request: http::Request<B>,
+    ) -> anyhow::Result<http::Response<BodyReader<'_, S>>> {
+        let (parts, body) = request.into_parts();
+        if let Some(authority) = parts.uri.authority() {
+            let target = url::Url::parse(&parts.uri.to_string()).context("parse request URI")?;
+            if target.host() != self.url().host()
+                || target.port_or_known_default() != self.url().port_or_known_default()
+            {
+                return Err(anyhow::Error::msg(format!(
+                    "request for {:?} sent to {:?}",
+                    authority.as_str(),
+                    self.host_header()?.to_string()
+                )));
+            }
+        }
+        if let Some(path_and_query) = parts.uri.path_and_query() {
+            self.set_resource(path_and_query.path(), path_and_query.query());
+        }
+
+        let method = Method::try_from(&parts.method)?;
+        let body = body.as_ref();
+        let mut headers = HeaderMap::from(&parts.headers);
+        let chunked = headers.contains("transfer-encoding");
+        let codings = headers.get_list("transfer-encoding");
+        if chunked
+            && !codings
+                .last()
+                .is_some_and(|c| c.eq_ignore_ascii_case("chunked"))
+        {
+            return Err(anyhow::Error::msg(
+                "request transfer-encoding does not end with chunked",
+            ));
+        }
+        if chunked && headers.contains("content-length") {
+            return Err(anyhow::Error::msg(
+                "request has both Content-Length and Transfer-Encoding",
+            ));
+        }
+        if !chunked
+            && !headers.contains("content-length")
+            && (!body.is_empty() || matches!(method, Method::Post | Method::Put | Method::Patch))
+        {
+            headers.append("Content-Length", body.len().to_string())?;
+        }
+        self.begin_request(method).await?;
+        self.request_headers(&headers).await?;
+        self.request_headers_end().await?;
+        if chunked {
+            self.request_chunk(body).await?;
+            self.request_chunks_end().await?;
+        } else if !body.is_empty() {
+            self.request_body_chunk(body).await?;
+        }
+        self.end_request();
+
+        self.response_begin().await?;
+        let response = http::Response::try_from(&*self)?;
+        Ok(response.map(|_| self.body()))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    #[test]
+    fn try_convert_headers() {
+        let mut headers = HeaderMap::new();
+        headers.append("Accept", "text/html").unwrap();
+        headers.append("accept", "*/*").unwrap();
+        let converted = http::HeaderMap::try_from(&headers).unwrap();
+        assert_eq!(2, converted.get_all(http::header::ACCEPT).iter().count());
+        assert_eq!(
+            vec!["text/html", "*/*"],
+            HeaderMap::from(&converted).get_list("Accept")
+        );
+        assert_eq!(http::Method::PATCH, http::Method::from(Method::Patch));
+        assert!(Method::try_from(&http::Method::from_bytes(b"PURGE").unwrap()).is_err());
+    }
+
+    #[tokio::test]
+    async fn try_send() {
+        let (client, mut server) = tokio::io::duplex(4096);
+        server
+            .write_all(b"HTTP/1.1 201 Created\r\nTransfer-Encoding: chunked\r\nX-Id: 7\r\n\r\n3\r\nabc\r\n0\r\n\r\n")
+            .await
+            .unwrap();
+        let url = url::Url::parse("http://example.org:8080/").unwrap();
+        let mut http = Context::from_socket(url, client);
+        let request = http::Request::post("/items?kind=a")
+            .header("Accept", "*/*")
+            .body(b"{}")
+            .unwrap();
+        let mut response = http.send(request).await.unwrap();
+        assert_eq!(http::StatusCode::CREATED, response.status());
+        assert_eq!("7", response.headers()["x-id"]);
+        let mut buf = [0; 16];
+        assert_eq!(3, response.body_mut().read(&mut buf).await.unwrap());
+        assert_eq!(b"abc", &buf[..3]);
+        assert_eq!(0, response.body_mut().read(&mut buf).await.unwrap());
+
+        let expected = "POST /items?kind=a HTTP/1.1\r\naccept: */*\r\nContent-Length: 2\r\nHost: example.org:8080\r\n\r\n{}";
+        let mut request = vec![0; expected.len()];
+        server.read_exact(&mut request).await.unwrap();
+        assert_eq!(expected, String::from_utf8(request).unwrap());
+    }
+
+    #[tokio::test]
+    async fn try_send_chunked() {
+        let (client, mut server) = tokio::io::duplex(4096);
+        server
+            .write_all(b"HTTP/1.1 204 No Content\r\n\r\n")
+            .await
+            .unwrap();
+        let url = url::Url::parse("http://example.org/").unwrap();
+        let mut http = Context::from_socket(url, client);
+        let request = http::Request::put("http://example.org:80/upload")
+            .header("Transfer-Encoding", "chunked")
+            .body(b"hello")
+            .unwrap();
+        let response = http.send(request).await.unwrap();
+        assert_eq!(http::StatusCode::NO_CONTENT, response.status());
+        drop(response);
+        drop(http);
+        let mut request = String::new();
+        server.read_to_string(&mut request).await.unwrap();
+        assert_eq!(
+            "PUT /upload HTTP/1.1\r\ntransfer-encoding: chunked\r\nHost: example.org\r\n\r\n5\r\nhello\r\n0\r\n\r\n",
+            request
+        );
+
+        for uri in [
+            "http://example.org:8080/",
+            "http://example.com/",
+            "https://example.org/",
+        ] {
+            let (client, _server) = tokio::io::duplex(4096);
+            let url = url::Url::parse("http://example.org/").unwrap();
+            let mut http = Context::from_socket(url, client);
+            let request = http::Request::get(uri).body(b"").unwrap();
+            assert!(http.send(request).await.is_err(), "{}", uri);
+        }
+    }
+
+    #[tokio::test]
+    async fn try_request_head() {
+        let socket = crate::test_util::MockSocket::new()
+            .then_read("PUT /items/1?force=1 HTTP/1.0\r\nHost: x\r\nX-Id: 7\r\n\r\n");
+        let mut connection = ServerConnection::new(socket);
+        assert!(connection.next_request().await.unwrap());
+        let request = http::Request::try_from(&connection).unwrap();
+        assert_eq!(http::Method::PUT, request.method());
+        assert_eq!("/items/1?force=1", request.uri());
+        assert_eq!(http::Version::HTTP_10, request.version());
+        assert_eq!("7", request.headers()["x-id"]);
+    }
+}
@@ -0,0 +1,118 @@
+//! JSON bodies, behind the `serde` feature.
+
+use super::{Context, MediaType};
+use crate::{HttpHeader, Socket};
+use anyhow::Context as AnyHowContext;
+
+/// Serialized array elements are sent once they add up to this many bytes.
+const ARRAY_CHUNK_SIZE: usize = 8 * 1024;
+
+impl<S: Socket> Context<S> {
+    /// Sends Content-Type and Content-Length, ends the headers and sends the serialized value.
+    pub async fn request_json<T: serde::Serialize + ?Sized>(
+        &mut self,
+        value: &T,
+    ) -> anyhow::Result<()> {
+        let body = serde_json::to_vec(value).context("serialize JSON body")?;
+        self.request_header(HttpHeader::ContentType(MediaType::application_json()))
+            .await?;
+        self.request_header(HttpHeader::ContentLength(body.len()))
+            .await?;
+        self.request_headers_end().await?;
+        self.request_body_chunk(body).await
+    }
+
+    /// Like [`Context::request_json`] for a JSON array, but serializes the elements
+    /// while sending them as a chunked body.
+    pub async fn request_json_array<T: serde::Serialize>(
+        &mut self,
+        items: impl IntoIterator<Item = T>,
+    ) -> anyhow::Result<()> {
+        self.request_header(HttpHeader::ContentType(MediaType::application_json()))
+            .await?;
+        self.request_header(HttpHeader::TransferEncodingChunked)
+            .await?;
+        self.request_headers_end().await?;
+        let mut chunk = b"[".to_vec();
+        for (i, item) in items.into_iter().enumerate() {
+            if i > 0 {
+                chunk.push(b',');
+            }
+            serde_json::to_writer(&mut chunk, &item).context("serialize JSON array element")?;
+            if chunk.len() >= ARRAY_CHUNK_SIZE {
+                self.request_chunk(&chunk).await?;
+                chunk.clear();
+            }
+        }
+        chunk.push(b']');
+        self.request_chunk(&chunk).await?;
+        self.request_chunks_end().await
+    }
+
+    /// Reads the rest of the response body like [`Context::bytes`] and deserializes it.
+    pub async fn json<T: serde::de::DeserializeOwned>(&mut self) -> anyhow::Result<T> {
+        let body = self.bytes().await?;
+        serde_json::from_slice(&body).context("parse JSON body")
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    #[tokio::test]
+    async fn try_json() {
+        let (client, mut server) = tokio::io::duplex(4096);
+        server
+            .write_all(b"HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: 17\r\n\r\n{\"id\": 7, \"a\": 1}")
+            .await
+            .unwrap();
+        let url = url::Url::parse("http://example.org/").unwrap();
+        let mut http = Context::from_socket(url, client);
+        http.response_begin().await.unwrap();
+        let value: std::collections::BTreeMap<String, u32> = http.json().await.unwrap();
+        assert_eq!(Some(&7), value.get("id"));
+    }
+
+    #[tokio::test]
+    async fn try_request_json() {
+        let (client, mut server) = tokio::io::duplex(64 * 1024);
+        let url = url::Url::parse("http://example.org/items").unwrap();
+        let mut http = Context::from_socket(url, client);
+        http.begin_request(crate::Method::Post).await.unwrap();
+        http.request_json(&[1, 2]).await.unwrap();
+        http.begin_request(crate::Method::Post).await.unwrap();
+        http.request_json_array((0..3000).map(|i| i * 2))
+            .await
+            .unwrap();
+        drop(http);
+
+        let mut request = String::new();
+        server.read_to_string(&mut request).await.unwrap();
+        let (single, array) = request.split_once("[1,2]").unwrap();
+        assert_eq!(
+            "POST /items HTTP/1.1\r\nContent-Type: application/json\r\nContent-Length: 5\r\nHost: example.org\r\n\r\n",
+            single
+        );
+        let (head, chunks) = array.split_once("\r\n\r\n").unwrap();
+        assert!(head.ends_with("Transfer-Encoding: chunked\r\nHost: example.org"));
+        let mut body = String::new();
+        let mut rest = chunks;
+        let mut chunk_count = 0;
+        loop {
+            let (size, tail) = rest.split_once("\r\n").unwrap();
+            let size = usize::from_str_radix(size, 16).unwrap();
+            if size == 0 {
+                assert_eq!("\r\n", tail);
+                break;
+            }
+            body.push_str(&tail[..size]);
+            chunk_count += 1;
+            rest = &tail[size + 2..];
+        }
+        assert!(chunk_count > 1);
+        let values: Vec<u32> = serde_json::from_str(&body).unwrap();
+        assert_eq!((0..3000).map(|i| i * 2).collect::<Vec<_>>(), values);
+    }
+}
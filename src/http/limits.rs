@@ -0,0 +1,212 @@
+/// Bounds on what a server may make the client buffer.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Limits {
+    /// Status line and header fields up to the empty line.
+    pub max_header_section: usize,
+    pub max_headers: usize,
+    /// Any single line: status line, header field, chunk size line.
+    pub max_line_length: usize,
+    pub max_chunk_size: usize,
+    pub max_trailer_size: usize,
+    pub max_body_size: usize,
+    /// Whole body collected by `Context::bytes`, `text` and `json`, pending event stream data.
+    pub max_buffered_body: usize,
+}
+
+impl Default for Limits {
+    fn default() -> Self {
+        Self {
+            max_header_section: 64 * 1024,
+            max_headers: 100,
+            max_line_length: 8 * 1024,
+            max_chunk_size: 16 * 1024 * 1024,
+            max_trailer_size: 16 * 1024,
+            max_body_size: usize::MAX,
+            max_buffered_body: 16 * 1024 * 1024,
+        }
+    }
+}
+
+impl Limits {
+    /// Checks the number and length of the lines of a start line and header section.
+    pub(crate) fn check_head(&self, head: &[u8]) -> Result<(), LimitError> {
+        let mut lines = head.split(|b| *b == b'\n');
+        if lines.clone().count() > self.max_headers + 1 {
+            return Err(LimitError::TooManyHeaders);
+        }
+        if lines.any(|line| line.len() > self.max_line_length + 1) {
+            return Err(LimitError::LineTooLong);
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LimitError {
+    HeaderSectionTooLarge,
+    TooManyHeaders,
+    LineTooLong,
+    ChunkTooLarge,
+    TrailersTooLarge,
+    BodyTooLarge,
+}
+
+impl std::fmt::Display for LimitError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::HeaderSectionTooLarge => "header section exceeds the limit",
+            Self::TooManyHeaders => "number of header fields exceeds the limit",
+            Self::LineTooLong => "line length exceeds the limit",
+            Self::ChunkTooLarge => "chunk size exceeds the limit",
+            Self::TrailersTooLarge => "trailer section exceeds the limit",
+            Self::BodyTooLarge => "body size exceeds the limit",
+        })
+    }
+}
+
+impl std::error::Error for LimitError {}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::http::Context;
+    use tokio::io::{AsyncWriteExt, DuplexStream};
+
+    async fn respond(response: &[u8], limits: Limits) -> (Context<DuplexStream>, DuplexStream) {
+        let (client, mut server) = tokio::io::duplex(64 * 1024);
+        server.write_all(response).await.unwrap();
+        let url = url::Url::parse("http://example.org/").unwrap();
+        let mut http = Context::from_socket(url, client);
+        http.set_limits(limits);
+        (http, server)
+    }
+
+    fn limit_error(e: anyhow::Error) -> LimitError {
+        *e.downcast_ref::<LimitError>().unwrap()
+    }
+
+    #[tokio::test]
+    async fn try_header_limits() {
+        let limits = Limits {
+            max_header_section: 64,
+            max_headers: 2,
+            ..Limits::default()
+        };
+        let (mut http, _server) =
+            respond(b"HTTP/1.1 200 OK\r\nA: 1\r\nB: 2\r\nC: 3\r\n\r\n", limits).await;
+        assert_eq!(
+            LimitError::TooManyHeaders,
+            limit_error(http.response_begin().await.unwrap_err())
+        );
+
+        let long = format!("HTTP/1.1 200 OK\r\nA: {}\r\n\r\n", "a".repeat(64));
+        let (mut http, _server) = respond(long.as_bytes(), limits).await;
+        assert_eq!(
+            LimitError::HeaderSectionTooLarge,
+            limit_error(http.response_begin().await.unwrap_err())
+        );
+    }
+
+    #[tokio::test]
+    async fn try_chunk_limits() {
+        let limits = Limits {
+            max_chunk_size: 0x10,
+            ..Limits::default()
+        };
+        let response =
+            b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n4;ext=1\r\nabcd\r\n11\r\n";
+        let (mut http, _server) = respond(response, limits).await;
+        http.response_begin().await.unwrap();
+        let mut buf = [0; 64];
+        assert_eq!(4, http.response_body_chunk_read(&mut buf).await.unwrap());
+        assert_eq!(
+            LimitError::ChunkTooLarge,
+            limit_error(http.response_body_chunk_read(&mut buf).await.unwrap_err())
+        );
+
+        let response =
+            b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\nffffffffffffffffffff\r\n";
+        let (mut http, _server) = respond(response, Limits::default()).await;
+        http.response_begin().await.unwrap();
+        assert_eq!(
+            LimitError::ChunkTooLarge,
+            limit_error(http.response_body_chunk_read(&mut buf).await.unwrap_err())
+        );
+    }
+
+    #[tokio::test]
+    async fn try_trailers() {
+        let response = b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n2\r\nok\r\n0\r\nExpires: never\r\n\r\n";
+        let (mut http, _server) = respond(response, Limits::default()).await;
+        http.response_begin().await.unwrap();
+        let mut buf = [0; 64];
+        assert_eq!(2, http.response_body_chunk_read(&mut buf).await.unwrap());
+        assert_eq!(0, http.response_body_chunk_read(&mut buf).await.unwrap());
+        assert!(!http.has_response());
+        assert_eq!(
+            vec![crate::HttpHeader::Custom {
+                name: "Expires".to_owned(),
+                value: "never".to_owned()
+            }],
+            http.response_trailer_iter().collect::<Vec<_>>()
+        );
+
+        let limits = Limits {
+            max_trailer_size: 8,
+            ..Limits::default()
+        };
+        let (mut http, _server) = respond(response, limits).await;
+        http.response_begin().await.unwrap();
+        assert_eq!(2, http.response_body_chunk_read(&mut buf).await.unwrap());
+        assert_eq!(
+            LimitError::TrailersTooLarge,
+            limit_error(http.response_body_chunk_read(&mut buf).await.unwrap_err())
+        );
+    }
+
+    #[tokio::test]
+    async fn try_body_limit() {
+        let limits = Limits {
+            max_body_size: 4,
+            ..Limits::default()
+        };
+        let (mut http, _server) =
+            respond(b"HTTP/1.1 200 OK\r\nContent-Length: 5\r\n\r\nhello", limits).await;
+        assert_eq!(
+            LimitError::BodyTooLarge,
+            limit_error(http.response_begin().await.unwrap_err())
+        );
+
+        let (mut http, server) = respond(b"HTTP/1.1 200 OK\r\n\r\nhello", limits).await;
+        drop(server);
+        http.response_begin().await.unwrap();
+        let mut buf = [0; 64];
+        assert_eq!(
+            LimitError::BodyTooLarge,
+            limit_error(http.response_body_chunk_read(&mut buf).await.unwrap_err())
+        );
+    }
+
+    #[tokio::test]
+    async fn try_buffered_body_limit() {
+        let limits = Limits {
+            max_buffered_body: 4,
+            ..Limits::default()
+        };
+        let (mut http, _server) =
+            respond(b"HTTP/1.1 200 OK\r\nContent-Length: 5\r\n\r\nhello", limits).await;
+        http.response_begin().await.unwrap();
+        assert_eq!(
+            LimitError::BodyTooLarge,
+            limit_error(http.bytes().await.unwrap_err())
+        );
+
+        let (mut http, server) = respond(b"HTTP/1.1 200 OK\r\n\r\nhello", limits).await;
+        drop(server);
+        http.response_begin().await.unwrap();
+        assert_eq!(
+            LimitError::BodyTooLarge,
+            limit_error(http.bytes().await.unwrap_err())
+        );
+    }
+}
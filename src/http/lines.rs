@@ -0,0 +1,165 @@
+//! Line-oriented response bodies such as NDJSON.
+
+use super::limits::LimitError;
+use super::{BodyReader, Context};
+use crate::Socket;
+use anyhow::Context as AnyHowContext;
+
+/// Longest line accepted unless changed with [`Lines::with_max_line_length`].
+const DEFAULT_MAX_LINE_LENGTH: usize = 1024 * 1024;
+
+/// Lines of a response body ended with LF or CRLF, the last one may have no ending.
+#[derive(Debug)]
+pub struct Lines<'a, S: Socket> {
+    body: BodyReader<'a, S>,
+    buf: Vec<u8>,
+    /// Bytes of `buf` known to have no LF.
+    scanned: usize,
+    max_line_length: usize,
+    eof: bool,
+}
+
+impl<'a, S: Socket> Lines<'a, S> {
+    pub fn new(body: BodyReader<'a, S>) -> Self {
+        Self {
+            body,
+            buf: vec![],
+            scanned: 0,
+            max_line_length: DEFAULT_MAX_LINE_LENGTH,
+            eof: false,
+        }
+    }
+
+    /// Longer lines fail with [`LimitError::LineTooLong`].
+    pub fn with_max_line_length(mut self, max_line_length: usize) -> Self {
+        self.max_line_length = max_line_length;
+        self
+    }
+
+    /// Returns `None` at the end of the body.
+    pub async fn next_line(&mut self) -> anyhow::Result<Option<String>> {
+        let mut chunk = [0; 4096];
+        loop {
+            if let Some(i) = self.buf[self.scanned..].iter().position(|b| *b == b'\n') {
+                let end = self.scanned + i;
+                let mut line: Vec<u8> = self.buf.drain(..end + 1).collect();
+                line.pop();
+                if line.last() == Some(&b'\r') {
+                    line.pop();
+                }
+                self.scanned = 0;
+                return self.checked(line).map(Some);
+            }
+            self.scanned = self.buf.len();
+            if self.buf.len() > self.max_line_length + 1 {
+                return Err(LimitError::LineTooLong.into());
+            }
+            if self.eof {
+                if self.buf.is_empty() {
+                    return Ok(None);
+                }
+                let line = std::mem::take(&mut self.buf);
+                self.scanned = 0;
+                return self.checked(line).map(Some);
+            }
+            let n = self.body.read(&mut chunk).await?;
+            self.eof = n == 0;
+            self.buf.extend_from_slice(&chunk[..n]);
+        }
+    }
+
+    fn checked(&self, line: Vec<u8>) -> anyhow::Result<String> {
+        if line.len() > self.max_line_length {
+            return Err(LimitError::LineTooLong.into());
+        }
+        String::from_utf8(line).context("body line contains non-UTF8")
+    }
+}
+
+/// Values of a newline-delimited JSON body, blank lines are skipped.
+#[cfg(feature = "serde")]
+#[derive(Debug)]
+pub struct Ndjson<'a, S: Socket, T> {
+    lines: Lines<'a, S>,
+    item: std::marker::PhantomData<fn() -> T>,
+}
+
+#[cfg(feature = "serde")]
+impl<'a, S: Socket, T: serde::de::DeserializeOwned> Ndjson<'a, S, T> {
+    pub fn new(lines: Lines<'a, S>) -> Self {
+        Self {
+            lines,
+            item: std::marker::PhantomData,
+        }
+    }
+
+    /// Returns `None` at the end of the body.
+    pub async fn next(&mut self) -> anyhow::Result<Option<T>> {
+        while let Some(line) = self.lines.next_line().await? {
+            if !line.trim().is_empty() {
+                return serde_json::from_str(&line)
+                    .context("parse NDJSON line")
+                    .map(Some);
+            }
+        }
+        Ok(None)
+    }
+}
+
+impl<S: Socket> Context<S> {
+    pub fn lines(&mut self) -> Lines<'_, S> {
+        Lines::new(self.body())
+    }
+
+    #[cfg(feature = "serde")]
+    pub fn ndjson<T: serde::de::DeserializeOwned>(&mut self) -> Ndjson<'_, S, T> {
+        Ndjson::new(self.lines())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test_util::MockSocket;
+
+    async fn respond(response: &'static [u8]) -> Context<MockSocket> {
+        let url = url::Url::parse("http://example.org/").unwrap();
+        let mut http = Context::from_socket(url, MockSocket::new().then_read(response));
+        http.response_begin().await.unwrap();
+        http
+    }
+
+    #[tokio::test]
+    async fn try_lines() {
+        let mut http = respond(b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n4\r\nfirs\r\n5\r\nt\r\nse\r\n6\r\ncond\nl\r\n3\r\nast\r\n0\r\n\r\n").await;
+        let mut lines = http.lines();
+        let mut collected = vec![];
+        while let Some(line) = lines.next_line().await.unwrap() {
+            collected.push(line);
+        }
+        assert_eq!(vec!["first", "second", "last"], collected);
+
+        let mut http =
+            respond(b"HTTP/1.1 200 OK\r\nContent-Length: 13\r\n\r\nshort\nlonger\n").await;
+        let mut lines = http.lines().with_max_line_length(5);
+        assert_eq!("short", lines.next_line().await.unwrap().unwrap());
+        let e = lines.next_line().await.unwrap_err();
+        assert_eq!(Some(&LimitError::LineTooLong), e.downcast_ref());
+    }
+
+    #[cfg(feature = "serde")]
+    #[tokio::test]
+    async fn try_ndjson() {
+        let mut http = respond(b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n9\r\n{\"id\":1}\n\r\n5\r\n\n{\"id\r\n4\r\n\":2}\r\n0\r\n\r\n").await;
+        let mut items = http.ndjson::<std::collections::BTreeMap<String, u32>>();
+        assert_eq!(
+            Some(1),
+            items.next().await.unwrap().unwrap().get("id").copied()
+        );
+        assert_eq!(
+            Some(2),
+            items.next().await.unwrap().unwrap().get("id").copied()
+        );
+        assert!(items.next().await.unwrap().is_none());
+    }
+}
@@ -0,0 +1,211 @@
+use super::headers::is_token;
+use std::fmt;
+use std::str::FromStr;
+
+/// `type/subtype` with parameters, e.g. `text/html; charset=utf-8`.
+///
+/// Type, subtype, parameter names and the charset value compare case-insensitively.
+#[derive(Debug, Clone, Eq)]
+pub struct MediaType {
+    type_: String,
+    subtype: String,
+    params: Vec<(String, String)>,
+}
+
+impl MediaType {
+    pub fn new(type_: &str, subtype: &str) -> Self {
+        Self {
+            type_: type_.to_ascii_lowercase(),
+            subtype: subtype.to_ascii_lowercase(),
+            params: vec![],
+        }
+    }
+
+    pub fn with_param(mut self, name: &str, value: impl Into<String>) -> Self {
+        let name = name.to_ascii_lowercase();
+        self.params.retain(|(n, _)| *n != name);
+        self.params.push((name, value.into()));
+        self
+    }
+
+    pub fn text_plain() -> Self {
+        Self::new("text", "plain").with_param("charset", "utf-8")
+    }
+
+    pub fn text_html() -> Self {
+        Self::new("text", "html").with_param("charset", "utf-8")
+    }
+
+    pub fn text_event_stream() -> Self {
+        Self::new("text", "event-stream")
+    }
+
+    pub fn application_json() -> Self {
+        Self::new("application", "json")
+    }
+
+    pub fn application_octet_stream() -> Self {
+        Self::new("application", "octet-stream")
+    }
+
+    pub fn application_www_form_urlencoded() -> Self {
+        Self::new("application", "x-www-form-urlencoded")
+    }
+
+    pub fn multipart_form_data(boundary: impl Into<String>) -> Self {
+        Self::new("multipart", "form-data").with_param("boundary", boundary)
+    }
+
+    pub fn type_(&self) -> &str {
+        &self.type_
+    }
+
+    pub fn subtype(&self) -> &str {
+        &self.subtype
+    }
+
+    /// `type/subtype` without parameters.
+    pub fn essence(&self) -> String {
+        format!("{}/{}", self.type_, self.subtype)
+    }
+
+    pub fn param(&self, name: &str) -> Option<&str> {
+        self.params
+            .iter()
+            .find(|(n, _)| n.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+
+    pub fn params(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.params
+            .iter()
+            .map(|(name, value)| (name.as_str(), value.as_str()))
+    }
+
+    pub fn charset(&self) -> Option<&str> {
+        self.param("charset")
+    }
+
+    pub fn boundary(&self) -> Option<&str> {
+        self.param("boundary")
+    }
+}
+
+impl PartialEq for MediaType {
+    fn eq(&self, other: &Self) -> bool {
+        self.type_ == other.type_
+            && self.subtype == other.subtype
+            && self.params.len() == other.params.len()
+            && self
+                .params
+                .iter()
+                .all(|(name, value)| match other.param(name) {
+                    Some(other) if name == "charset" => value.eq_ignore_ascii_case(other),
+                    Some(other) => value == other,
+                    None => false,
+                })
+    }
+}
+
+impl FromStr for MediaType {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (essence, mut rest) = s.split_once(';').unwrap_or((s, ""));
+        let (type_, subtype) = essence
+            .trim()
+            .split_once('/')
+            .filter(|(type_, subtype)| is_token(type_) && is_token(subtype))
+            .ok_or_else(|| anyhow::Error::msg("media type is not type/subtype"))?;
+        let mut media_type = Self::new(type_, subtype);
+        loop {
+            rest = rest.trim_start_matches([' ', '\t', ';']);
+            if rest.is_empty() {
+                break;
+            }
+            let (name, after_name) = rest
+                .split_once('=')
+                .ok_or_else(|| anyhow::Error::msg("media type parameter has no value"))?;
+            if !is_token(name) {
+                return Err(anyhow::Error::msg(
+                    "media type parameter name is not a token",
+                ));
+            }
+            let (value, after_value) = match after_name.strip_prefix('"') {
+                Some(quoted) => unquote(quoted)?,
+                None => {
+                    let end = after_name.find(';').unwrap_or(after_name.len());
+                    (after_name[..end].trim().to_owned(), &after_name[end..])
+                }
+            };
+            if media_type.param(name).is_none() {
+                media_type = media_type.with_param(name, value);
+            }
+            rest = after_value;
+        }
+        Ok(media_type)
+    }
+}
+
+impl fmt::Display for MediaType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}/{}", self.type_, self.subtype)?;
+        for (name, value) in &self.params {
+            if is_token(value) {
+                write!(f, "; {}={}", name, value)?;
+            } else {
+                write!(
+                    f,
+                    "; {}=\"{}\"",
+                    name,
+                    value.replace('\\', "\\\\").replace('"', "\\\"")
+                )?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Value of a quoted-string whose opening quote is already consumed and the rest of input.
+fn unquote(s: &str) -> anyhow::Result<(String, &str)> {
+    let mut value = String::new();
+    let mut chars = s.char_indices();
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '"' => return Ok((value, &s[i + 1..])),
+            '\\' => value.push(
+                chars
+                    .next()
+                    .map(|(_, escaped)| escaped)
+                    .ok_or_else(|| anyhow::Error::msg("quoted-string ends with backslash"))?,
+            ),
+            c => value.push(c),
+        }
+    }
+    Err(anyhow::Error::msg("quoted-string has no closing quote"))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn try_parse_media_type() {
+        let media_type: MediaType = "Multipart/Form-Data; Boundary=\"a \\\"b\\\" c\";charset=UTF-8"
+            .parse()
+            .unwrap();
+        assert_eq!("multipart/form-data", media_type.essence());
+        assert_eq!(Some("a \"b\" c"), media_type.boundary());
+        assert_eq!(Some("UTF-8"), media_type.charset());
+        assert_eq!(
+            "multipart/form-data; boundary=\"a \\\"b\\\" c\"; charset=UTF-8",
+            media_type.to_string()
+        );
+        assert_eq!(
+            MediaType::text_html(),
+            "TEXT/html;charset=\"UTF-8\"".parse().unwrap()
+        );
+        assert!("text".parse::<MediaType>().is_err());
+        assert!("text/plain; charset=\"utf-8".parse::<MediaType>().is_err());
+    }
+}
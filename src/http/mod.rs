@@ -1,9 +1,32 @@
+pub mod body;
+mod body_decoder;
 mod context;
+pub mod form;
+pub mod framing;
+pub mod header_map;
+pub mod header_values;
 pub mod headers;
+#[cfg(feature = "http")]
+mod interop;
+#[cfg(feature = "serde")]
+mod json;
+pub mod limits;
+pub mod lines;
+pub mod media_type;
 pub mod method;
+pub mod multipart;
+pub mod server;
+pub mod sse;
 pub mod status_line;
+pub mod text;
 
+pub use body::BodyReader;
 pub use context::Context;
+pub use form::Form;
+pub use header_map::HeaderMap;
+pub use media_type::MediaType;
+pub use server::ServerConnection;
+pub use text::TextDecoder;
 
 fn end_of_line(line: &[u8]) -> usize {
     line.windows(2)
@@ -26,3 +49,16 @@ fn skip_line(line: &[u8]) -> &[u8] {
         &line[(end_of_line + 2)..]
     }
 }
+
+/// Unpredictable but not cryptographically strong bits for multipart boundaries.
+pub(crate) fn random_u64() -> u64 {
+    use std::hash::{BuildHasher, Hasher};
+    let mut hasher = std::collections::hash_map::RandomState::new().build_hasher();
+    hasher.write_u128(
+        std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_nanos(),
+    );
+    hasher.finish()
+}
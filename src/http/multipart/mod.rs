@@ -0,0 +1,7 @@
+//! `multipart/*` bodies of RFC 2046.
+
+mod parser;
+mod upload;
+
+pub use parser::{MultipartReader, PartReader};
+pub use upload::{Multipart, Part};
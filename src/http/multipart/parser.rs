@@ -0,0 +1,280 @@
+use crate::http::framing::Strictness;
+use crate::http::headers::parse_headers;
+use crate::http::limits::{LimitError, Limits};
+use crate::http::{BodyReader, Context, MediaType};
+use crate::{HttpHeader, Socket};
+use anyhow::Context as AnyHowContext;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Position {
+    Preamble,
+    AfterDelimiter,
+    Body,
+    Done,
+}
+
+/// Streaming parser of `multipart/*` response bodies such as `multipart/mixed`
+/// and `multipart/byteranges`.
+#[derive(Debug)]
+pub struct MultipartReader<'a, S: Socket> {
+    body: BodyReader<'a, S>,
+    /// `CRLF--boundary`, the CRLF belongs to the delimiter and not to the part body.
+    delimiter: Vec<u8>,
+    buf: Vec<u8>,
+    position: Position,
+    /// Header section of a single part, the body of a part is never buffered.
+    max_part_headers: usize,
+}
+
+impl<'a, S: Socket> MultipartReader<'a, S> {
+    pub fn new(body: BodyReader<'a, S>, boundary: &str) -> Self {
+        Self::with_limits(body, boundary, Limits::default())
+    }
+
+    /// The header section of a part over `max_header_section` fails with
+    /// [`LimitError::HeaderSectionTooLarge`].
+    pub fn with_limits(body: BodyReader<'a, S>, boundary: &str, limits: Limits) -> Self {
+        Self {
+            body,
+            delimiter: format!("\r\n--{}", boundary).into_bytes(),
+            // The first delimiter may start the body without a preceding CRLF.
+            buf: b"\r\n".to_vec(),
+            position: Position::Preamble,
+            max_part_headers: limits.max_header_section,
+        }
+    }
+
+    /// Skips what is left of the current part and reads the headers of the next one.
+    pub async fn next_part(&mut self) -> anyhow::Result<Option<PartReader<'_, 'a, S>>> {
+        match self.position {
+            Position::Preamble => self.skip_preamble().await?,
+            Position::Body => {
+                let mut scratch = [0; 4096];
+                while self.read_body(&mut scratch).await? > 0 {}
+            }
+            Position::AfterDelimiter => {}
+            Position::Done => return Ok(None),
+        }
+
+        while self.buf.len() < 2 {
+            self.fill().await?;
+        }
+        if self.buf.starts_with(b"--") {
+            // The epilogue is dropped so that the response body ends.
+            self.position = Position::Done;
+            self.buf.clear();
+            let mut scratch = [0; 4096];
+            while self.body.read(&mut scratch).await? > 0 {}
+            return Ok(None);
+        }
+        // Transport padding after the boundary is ignored.
+        let end = self.find_bounded(b"\r\n").await?;
+        self.buf.drain(..end + 2);
+
+        let headers = if self.buf.starts_with(b"\r\n") {
+            self.buf.drain(..2);
+            vec![]
+        } else {
+            let end = self.find_bounded(b"\r\n\r\n").await?;
+            let block: Vec<u8> = self.buf.drain(..end + 4).collect();
+            parse_headers(&block[..end + 2], Strictness::Lenient)?
+        };
+        self.position = Position::Body;
+        Ok(Some(PartReader {
+            reader: self,
+            headers,
+        }))
+    }
+
+    async fn skip_preamble(&mut self) -> anyhow::Result<()> {
+        loop {
+            if let Some(i) = find(&self.buf, &self.delimiter) {
+                self.buf.drain(..i + self.delimiter.len());
+                self.position = Position::AfterDelimiter;
+                return Ok(());
+            }
+            let keep = self.delimiter.len() - 1;
+            if self.buf.len() > keep {
+                self.buf.drain(..self.buf.len() - keep);
+            }
+            self.fill().await?;
+        }
+    }
+
+    /// Returns 0 at the end of the current part.
+    async fn read_body(&mut self, buf: &mut [u8]) -> anyhow::Result<usize> {
+        if self.position != Position::Body || buf.is_empty() {
+            return Ok(0);
+        }
+        loop {
+            let available = match find(&self.buf, &self.delimiter) {
+                Some(0) => {
+                    self.buf.drain(..self.delimiter.len());
+                    self.position = Position::AfterDelimiter;
+                    return Ok(0);
+                }
+                Some(i) => i,
+                None => self.buf.len().saturating_sub(self.delimiter.len() - 1),
+            };
+            if available > 0 {
+                let n = available.min(buf.len());
+                buf[..n].copy_from_slice(&self.buf[..n]);
+                self.buf.drain(..n);
+                return Ok(n);
+            }
+            self.fill().await?;
+        }
+    }
+
+    /// Position of `needle`, which must be within `max_part_headers` bytes.
+    async fn find_bounded(&mut self, needle: &[u8]) -> anyhow::Result<usize> {
+        loop {
+            let found = find(&self.buf, needle);
+            if found.unwrap_or(self.buf.len()) > self.max_part_headers {
+                return Err(LimitError::HeaderSectionTooLarge.into());
+            }
+            if let Some(i) = found {
+                return Ok(i);
+            }
+            self.fill().await?;
+        }
+    }
+
+    async fn fill(&mut self) -> anyhow::Result<()> {
+        let mut chunk = [0; 4096];
+        let n = self.body.read(&mut chunk).await?;
+        if n == 0 {
+            return Err(anyhow::Error::msg(
+                "multipart body ends before the close delimiter",
+            ));
+        }
+        self.buf.extend_from_slice(&chunk[..n]);
+        Ok(())
+    }
+}
+
+/// Headers and body of the current part of a [`MultipartReader`].
+#[derive(Debug)]
+pub struct PartReader<'r, 'a, S: Socket> {
+    reader: &'r mut MultipartReader<'a, S>,
+    headers: Vec<HttpHeader>,
+}
+
+impl<S: Socket> PartReader<'_, '_, S> {
+    pub fn headers(&self) -> &[HttpHeader] {
+        &self.headers
+    }
+
+    pub fn media_type(&self) -> Option<&MediaType> {
+        self.headers.iter().find_map(|header| match header {
+            HttpHeader::ContentType(media_type) => Some(media_type),
+            _ => None,
+        })
+    }
+
+    /// Returns 0 at the end of the part.
+    pub async fn read(&mut self, buf: &mut [u8]) -> anyhow::Result<usize> {
+        self.reader.read_body(buf).await
+    }
+}
+
+impl<S: Socket> Context<S> {
+    /// Parser of a `multipart/*` response with the boundary of its Content-Type
+    /// and the limits of the context.
+    pub fn multipart(&mut self) -> anyhow::Result<MultipartReader<'_, S>> {
+        let media_type = self
+            .response_media_type()?
+            .filter(|media_type| media_type.type_() == "multipart")
+            .context("response is not multipart")?;
+        let boundary = media_type
+            .boundary()
+            .context("multipart response has no boundary")?;
+        let limits = self.limits();
+        Ok(MultipartReader::with_limits(self.body(), boundary, limits))
+    }
+}
+
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack
+        .windows(needle.len())
+        .position(|window| window == needle)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::http::header_values::ContentRange;
+    use crate::test_util::MockSocket;
+
+    #[tokio::test]
+    async fn try_byteranges() {
+        let body = "preamble\r\n--THIS\r\nContent-Type: text/plain\r\nContent-Range: bytes 0-4/20\r\n\r\nhello\r\n--THIS  \r\nContent-Range: bytes 10-19/20\r\n\r\nline\r\n--TH\r\nend\r\n--THIS--\r\nepilogue";
+        let response = format!(
+            "HTTP/1.1 206 Partial Content\r\nContent-Type: multipart/byteranges; boundary=THIS\r\nContent-Length: {}\r\n\r\n{}",
+            body.len(),
+            body
+        );
+        // 16-byte reads split delimiters and header blocks.
+        let socket = response
+            .as_bytes()
+            .chunks(16)
+            .fold(MockSocket::new(), MockSocket::then_read);
+        let url = url::Url::parse("http://example.org/").unwrap();
+        let mut http = Context::from_socket(url, socket);
+        http.response_begin().await.unwrap();
+        let mut multipart = http.multipart().unwrap();
+
+        let mut part = multipart.next_part().await.unwrap().unwrap();
+        assert_eq!("text/plain", part.media_type().unwrap().essence());
+        let mut buf = [0; 3];
+        assert_eq!(3, part.read(&mut buf).await.unwrap());
+        assert_eq!(b"hel", &buf);
+
+        let mut part = multipart.next_part().await.unwrap().unwrap();
+        assert_eq!(
+            &[HttpHeader::ContentRange(ContentRange {
+                unit: "bytes".to_owned(),
+                range: Some((10, 19)),
+                complete_length: Some(20),
+            })],
+            part.headers()
+        );
+        let mut content = vec![];
+        loop {
+            let n = part.read(&mut buf).await.unwrap();
+            if n == 0 {
+                break;
+            }
+            content.extend_from_slice(&buf[..n]);
+        }
+        assert_eq!(b"line\r\n--TH\r\nend", content.as_slice());
+        assert!(multipart.next_part().await.unwrap().is_none());
+        assert!(!http.has_response());
+    }
+
+    #[tokio::test]
+    async fn try_part_header_limit() {
+        let body = format!(
+            "--B\r\nX-Long: {}\r\n\r\npart\r\n--B--\r\n",
+            "a".repeat(512)
+        );
+        let response = format!(
+            "HTTP/1.1 200 OK\r\nContent-Type: multipart/mixed; boundary=B\r\nContent-Length: {}\r\n\r\n{}",
+            body.len(),
+            body
+        );
+        let url = url::Url::parse("http://example.org/").unwrap();
+        let mut http = Context::from_socket(url, MockSocket::new().then_read(response));
+        http.set_limits(Limits {
+            max_header_section: 256,
+            ..Limits::default()
+        });
+        http.response_begin().await.unwrap();
+        let mut multipart = http.multipart().unwrap();
+        let err = multipart.next_part().await.unwrap_err();
+        assert_eq!(
+            Some(&LimitError::HeaderSectionTooLarge),
+            err.downcast_ref::<LimitError>()
+        );
+    }
+}
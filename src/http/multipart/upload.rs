@@ -0,0 +1,343 @@
+use crate::http::{random_u64, Context, MediaType};
+use crate::{HttpHeader, Socket};
+use anyhow::Context as AnyHowContext;
+use std::fmt::Write;
+use tokio::io::{AsyncRead, AsyncReadExt};
+
+/// Request body data is sent in writes of about this size.
+const WRITE_SIZE: usize = 8 * 1024;
+
+/// One field of a `multipart/form-data` body.
+pub struct Part<'a> {
+    file_name: Option<String>,
+    media_type: Option<MediaType>,
+    body: PartBody<'a>,
+}
+
+enum PartBody<'a> {
+    Bytes(Vec<u8>),
+    Reader {
+        reader: Box<dyn AsyncRead + Send + Unpin + 'a>,
+        size: Option<usize>,
+    },
+}
+
+impl<'a> Part<'a> {
+    pub fn text(value: impl Into<String>) -> Self {
+        Self::bytes(value.into().into_bytes())
+    }
+
+    pub fn bytes(value: impl Into<Vec<u8>>) -> Self {
+        Self {
+            file_name: None,
+            media_type: None,
+            body: PartBody::Bytes(value.into()),
+        }
+    }
+
+    /// Streams the part from `reader`, `size` allows a Content-Length for the whole body.
+    pub fn reader(reader: impl AsyncRead + Send + Unpin + 'a, size: Option<usize>) -> Self {
+        Self {
+            file_name: None,
+            media_type: None,
+            body: PartBody::Reader {
+                reader: Box::new(reader),
+                size,
+            },
+        }
+    }
+
+    pub fn with_file_name(mut self, file_name: impl Into<String>) -> Self {
+        self.file_name = Some(file_name.into());
+        self
+    }
+
+    pub fn with_media_type(mut self, media_type: MediaType) -> Self {
+        self.media_type = Some(media_type);
+        self
+    }
+
+    fn size(&self) -> Option<usize> {
+        match &self.body {
+            PartBody::Bytes(bytes) => Some(bytes.len()),
+            PartBody::Reader { reader: _, size } => *size,
+        }
+    }
+
+    /// Header section of the part including the empty line.
+    fn headers(&self, name: &str) -> String {
+        let mut headers = format!("Content-Disposition: form-data; name=\"{}\"", quote(name));
+        if let Some(file_name) = &self.file_name {
+            let ascii: String = file_name
+                .chars()
+                .map(|c| if c.is_ascii() { c } else { '_' })
+                .collect();
+            write!(headers, "; filename=\"{}\"", quote(&ascii)).unwrap();
+            if !file_name.is_ascii() {
+                write!(headers, "; filename*=UTF-8''{}", ext_value(file_name)).unwrap();
+            }
+        }
+        headers.push_str("\r\n");
+        if let Some(media_type) = &self.media_type {
+            write!(
+                headers,
+                "{}\r\n",
+                HttpHeader::ContentType(media_type.clone())
+            )
+            .unwrap();
+        }
+        headers.push_str("\r\n");
+        headers
+    }
+}
+
+impl std::fmt::Debug for Part<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Part")
+            .field("file_name", &self.file_name)
+            .field("media_type", &self.media_type)
+            .field("size", &self.size())
+            .finish()
+    }
+}
+
+/// `multipart/form-data` request body of RFC 7578.
+#[derive(Debug)]
+pub struct Multipart<'a> {
+    boundary: String,
+    parts: Vec<(String, Part<'a>)>,
+}
+
+impl Default for Multipart<'_> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<'a> Multipart<'a> {
+    /// Empty body with a random boundary.
+    pub fn new() -> Self {
+        Self::with_boundary(format!(
+            "----------{:016x}{:016x}",
+            random_u64(),
+            random_u64()
+        ))
+    }
+
+    pub fn with_boundary(boundary: impl Into<String>) -> Self {
+        Self {
+            boundary: boundary.into(),
+            parts: vec![],
+        }
+    }
+
+    pub fn with_part(mut self, name: impl Into<String>, part: Part<'a>) -> Self {
+        self.parts.push((name.into(), part));
+        self
+    }
+
+    pub fn with_text(self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.with_part(name, Part::text(value))
+    }
+
+    pub fn boundary(&self) -> &str {
+        &self.boundary
+    }
+
+    pub fn media_type(&self) -> MediaType {
+        MediaType::multipart_form_data(&self.boundary)
+    }
+
+    /// Size of the whole body when the size of every part is known.
+    pub fn content_length(&self) -> Option<usize> {
+        let mut length = self.close_delimiter().len();
+        for (name, part) in &self.parts {
+            length += self.delimiter().len() + part.headers(name).len() + part.size()? + 2;
+        }
+        Some(length)
+    }
+
+    fn delimiter(&self) -> String {
+        format!("--{}\r\n", self.boundary)
+    }
+
+    fn close_delimiter(&self) -> String {
+        format!("--{}--\r\n", self.boundary)
+    }
+}
+
+impl<S: Socket> Context<S> {
+    /// Sends Content-Type and the framing headers, ends the headers and streams the parts.
+    ///
+    /// The body has Content-Length when every part size is known and is chunked otherwise.
+    pub async fn request_multipart(&mut self, multipart: Multipart<'_>) -> anyhow::Result<()> {
+        let content_length = multipart.content_length();
+        self.request_header(HttpHeader::ContentType(multipart.media_type()))
+            .await?;
+        match content_length {
+            Some(length) => self.request_header(HttpHeader::ContentLength(length)),
+            None => self.request_header(HttpHeader::TransferEncodingChunked),
+        }
+        .await?;
+        self.request_headers_end().await?;
+
+        let result = self
+            .request_multipart_body(multipart, content_length.is_none())
+            .await;
+        if result.is_err() {
+            self.set_unusable();
+        }
+        result
+    }
+
+    async fn request_multipart_body(
+        &mut self,
+        multipart: Multipart<'_>,
+        chunked: bool,
+    ) -> anyhow::Result<()> {
+        let delimiter = multipart.delimiter();
+        let close_delimiter = multipart.close_delimiter();
+        let mut pending = vec![];
+        let mut buf = vec![0; WRITE_SIZE];
+        for (name, part) in multipart.parts {
+            pending.extend_from_slice(delimiter.as_bytes());
+            pending.extend_from_slice(part.headers(&name).as_bytes());
+            match part.body {
+                PartBody::Bytes(bytes) => pending.extend_from_slice(&bytes),
+                PartBody::Reader { mut reader, size } => {
+                    // A declared size is already in Content-Length, no byte past it goes out.
+                    let mut limited = (&mut reader).take(size.map_or(u64::MAX, |size| size as u64));
+                    let mut total = 0;
+                    loop {
+                        let n = limited
+                            .read(&mut buf)
+                            .await
+                            .with_context(|| format!("read multipart part {:?}", name))?;
+                        if n == 0 {
+                            break;
+                        }
+                        total += n;
+                        pending.extend_from_slice(&buf[..n]);
+                        if pending.len() >= WRITE_SIZE {
+                            self.request_body_data(&pending, chunked).await?;
+                            pending.clear();
+                        }
+                    }
+                    if let Some(size) = size {
+                        let longer = reader
+                            .read(&mut [0; 1])
+                            .await
+                            .with_context(|| format!("read multipart part {:?}", name))?
+                            > 0;
+                        if total != size || longer {
+                            return Err(anyhow::Error::msg(format!(
+                                "multipart part {:?} does not have the {} bytes it declared",
+                                name, size
+                            )));
+                        }
+                    }
+                }
+            }
+            pending.extend_from_slice(b"\r\n");
+        }
+        pending.extend_from_slice(close_delimiter.as_bytes());
+        self.request_body_data(&pending, chunked).await?;
+        if chunked {
+            self.request_chunks_end().await?;
+        }
+        Ok(())
+    }
+
+    async fn request_body_data(&mut self, data: &[u8], chunked: bool) -> anyhow::Result<()> {
+        if chunked {
+            self.request_chunk(data).await
+        } else {
+            self.request_body_chunk(data).await
+        }
+    }
+}
+
+/// Escapes a quoted-string the way browsers do for form-data names.
+fn quote(s: &str) -> String {
+    s.replace('"', "%22")
+        .replace('\r', "%0D")
+        .replace('\n', "%0A")
+}
+
+/// Percent-encoding of an RFC 8187 ext-value.
+fn ext_value(s: &str) -> String {
+    let mut encoded = String::new();
+    for b in s.bytes() {
+        if b.is_ascii_alphanumeric() || b"!#$&+-.^_`|~".contains(&b) {
+            encoded.push(b as char);
+        } else {
+            write!(encoded, "%{:02X}", b).unwrap();
+        }
+    }
+    encoded
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test_util::MockSocket;
+
+    fn upload() -> Multipart<'static> {
+        Multipart::with_boundary("XyZ")
+            .with_text("title", "report")
+            .with_part(
+                "file",
+                Part::reader(&b"a,b\n1,2\n"[..], Some(8))
+                    .with_file_name("отчёт.csv")
+                    .with_media_type(MediaType::new("text", "csv")),
+            )
+    }
+
+    const BODY: &str = "--XyZ\r\nContent-Disposition: form-data; name=\"title\"\r\n\r\nreport\r\n--XyZ\r\nContent-Disposition: form-data; name=\"file\"; filename=\"_____.csv\"; filename*=UTF-8''%D0%BE%D1%82%D1%87%D1%91%D1%82.csv\r\nContent-Type: text/csv\r\n\r\na,b\n1,2\n\r\n--XyZ--\r\n";
+
+    async fn sent(multipart: Multipart<'_>) -> String {
+        let socket = MockSocket::new();
+        let writes = socket.writes();
+        let url = url::Url::parse("http://example.org/upload").unwrap();
+        let mut http = Context::from_socket(url, socket);
+        http.begin_request(crate::Method::Post).await.unwrap();
+        http.request_multipart(multipart).await.unwrap();
+        String::from_utf8(writes.bytes()).unwrap()
+    }
+
+    #[tokio::test]
+    async fn try_multipart_upload() {
+        assert_eq!(Some(BODY.len()), upload().content_length());
+        let request = sent(upload()).await;
+        let (head, body) = request.split_once("\r\n\r\n").unwrap();
+        assert!(head.contains("Content-Type: multipart/form-data; boundary=XyZ\r\n"));
+        assert!(head.contains(&format!("Content-Length: {}\r\n", BODY.len())));
+        assert_eq!(BODY, body);
+
+        let unknown_size = Multipart::with_boundary("XyZ").with_part(
+            "file",
+            Part::reader(&b"a,b\n"[..], None).with_file_name("a.csv"),
+        );
+        let request = sent(unknown_size).await;
+        assert!(request.contains("Transfer-Encoding: chunked\r\n"));
+        assert!(request.ends_with("\r\n\r\na,b\n\r\n--XyZ--\r\n\r\n0\r\n\r\n"));
+        assert_ne!(Multipart::new().boundary(), Multipart::new().boundary());
+    }
+
+    #[tokio::test]
+    async fn try_part_longer_than_declared() {
+        for (content, declared) in [(&b"abcdef"[..], 3), (&b"ab"[..], 3)] {
+            let socket = MockSocket::new();
+            let writes = socket.writes();
+            let url = url::Url::parse("http://example.org/upload").unwrap();
+            let mut http = Context::from_socket(url, socket);
+            http.begin_request(crate::Method::Post).await.unwrap();
+            let multipart = Multipart::with_boundary("XyZ")
+                .with_part("file", Part::reader(content, Some(declared)));
+            assert!(http.request_multipart(multipart).await.is_err());
+            assert!(!writes.bytes().ends_with(b"--XyZ--\r\n"));
+            assert!(!String::from_utf8(writes.bytes()).unwrap().contains("def"));
+            assert!(http.begin_request(crate::Method::Get).await.is_err());
+        }
+    }
+}
@@ -0,0 +1,692 @@
+//! Server side of an HTTP/1.1 connection: requests in, responses out.
+
+use crate::{bbuf::Buffer, HttpHeader, Method, Socket};
+use anyhow::Context as AnyHowContext;
+use tokio::net::TcpStream;
+
+use super::body_decoder::BodyDecoder;
+use super::framing::{request_framing, Framing, Strictness};
+use super::header_map::HeaderMap;
+use super::headers::{check_field, trim_ows, HeaderIter};
+use super::limits::{LimitError, Limits};
+use super::{get_line, skip_line};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Response {
+    /// No request has been read yet.
+    Idle,
+    /// A request is waiting for its response.
+    Pending,
+    /// Status line sent, header fields may follow.
+    Head {
+        code: u16,
+    },
+    /// Header section sent, the body goes out with this framing.
+    Body(Framing),
+    Complete,
+}
+
+/// Accepted connection serving requests one after another, pipelined ones included.
+///
+/// ```no_run
+/// # async fn serve(socket: tokio::net::TcpStream) -> anyhow::Result<()> {
+/// use http_chunked::http::server::ServerConnection;
+/// use http_chunked::HeaderMap;
+///
+/// let mut connection = ServerConnection::new(socket);
+/// while connection.next_request().await? {
+///     let body = connection.request_bytes().await?;
+///     connection.respond(200, "OK", &HeaderMap::new(), body).await?;
+/// }
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct ServerConnection<S: Socket = TcpStream> {
+    buffer: Buffer<S>,
+    request_meta: Vec<u8>,
+    request_headers: HeaderMap,
+    method: Method,
+    target: String,
+    http_1_0: bool,
+    keep_alive: bool,
+    expect_continue: bool,
+    decoder: BodyDecoder,
+    response: Response,
+    /// Framing announced by the header fields of the response.
+    response_framing: Option<Framing>,
+    close_sent: bool,
+    response_written: usize,
+    strictness: Strictness,
+    limits: Limits,
+}
+
+impl<S: Socket> ServerConnection<S> {
+    pub fn new(socket: S) -> Self {
+        Self {
+            buffer: Buffer::new(socket),
+            request_meta: vec![],
+            request_headers: HeaderMap::new(),
+            method: Method::Get,
+            target: String::new(),
+            http_1_0: false,
+            keep_alive: true,
+            expect_continue: false,
+            decoder: BodyDecoder::default(),
+            response: Response::Idle,
+            response_framing: None,
+            close_sent: false,
+            response_written: 0,
+            strictness: Strictness::default(),
+            limits: Limits::default(),
+        }
+    }
+
+    /// Exceeding any of the limits fails with [`LimitError`].
+    pub fn set_limits(&mut self, limits: Limits) {
+        self.limits = limits;
+    }
+
+    /// Strict by default: ambiguous request framing fails with [`super::framing::FramingError`].
+    pub fn set_strictness(&mut self, strictness: Strictness) {
+        self.strictness = strictness;
+    }
+
+    /// Reads the next request head, `false` once the connection is done.
+    ///
+    /// The unread body of the previous request is discarded.
+    /// Fails while the previous response is unfinished, and after answering
+    /// `400 Bad Request` to an HTTP/1.1 request without a Host.
+    pub async fn next_request(&mut self) -> anyhow::Result<bool> {
+        match self.response {
+            Response::Idle => {}
+            Response::Complete if !self.keep_alive => return Ok(false),
+            Response::Complete => {
+                let mut buf = [0; 4096];
+                while self.request_body_read(&mut buf).await? > 0 {}
+            }
+            _ => {
+                return Err(anyhow::Error::msg(
+                    "cannot read a request before the end of the previous response",
+                ))
+            }
+        }
+        loop {
+            if !self.buffer.has_data().await? {
+                return Ok(false);
+            }
+            // Empty lines before a request line are ignored, see RFC 9112 §2.2.
+            if !self.buffer.buffer().starts_with(b"\r\n") {
+                break;
+            }
+            self.buffer.read_line(self.limits.max_line_length).await?;
+        }
+        self.request_meta = self
+            .buffer
+            .read_until_and_chop(
+                b"\r\n\r\n",
+                self.limits.max_header_section,
+                LimitError::HeaderSectionTooLarge,
+            )
+            .await?;
+        self.limits.check_head(&self.request_meta)?;
+        self.parse_request_line()?;
+
+        self.request_headers =
+            HeaderMap::parse_fields(skip_line(&self.request_meta), self.strictness)?;
+        let framing = request_framing(self.request_headers.str_fields(), self.strictness)?;
+        let connection = self.request_headers.get_list("connection");
+        let has_token = |token| connection.iter().any(|t| t.eq_ignore_ascii_case(token));
+        self.keep_alive = if self.http_1_0 {
+            has_token("keep-alive")
+        } else {
+            !has_token("close")
+        };
+        self.expect_continue = !self.http_1_0
+            && self
+                .request_headers
+                .get_str("expect")
+                .is_some_and(|value| value.trim().eq_ignore_ascii_case("100-continue"));
+        self.decoder = BodyDecoder::new(framing, &self.limits)?;
+        self.response = Response::Pending;
+        self.response_framing = None;
+        self.close_sent = false;
+        self.response_written = 0;
+        // RFC 9112 §3.2: an HTTP/1.1 request without exactly one Host is answered with 400.
+        if !self.http_1_0 && self.request_headers.get_all("host").count() != 1 {
+            self.keep_alive = false;
+            self.respond(400, "Bad Request", &HeaderMap::new(), "")
+                .await?;
+            return Err(anyhow::Error::msg(
+                "HTTP/1.1 request does not have exactly one Host",
+            ));
+        }
+        Ok(true)
+    }
+
+    fn parse_request_line(&mut self) -> anyhow::Result<()> {
+        let request_line = std::str::from_utf8(get_line(&self.request_meta))
+            .context("request line contains non-UTF8 bytes")?;
+        let mut parts = request_line.split(' ');
+        let (Some(method), Some(target), Some(version), None) =
+            (parts.next(), parts.next(), parts.next(), parts.next())
+        else {
+            return Err(anyhow::Error::msg(format!(
+                "malformed request line {:?}",
+                request_line
+            )));
+        };
+        if target.is_empty() {
+            return Err(anyhow::Error::msg("request line has no request-target"));
+        }
+        self.http_1_0 = match version {
+            "HTTP/1.1" => false,
+            "HTTP/1.0" => true,
+            _ => {
+                return Err(anyhow::Error::msg(
+                    "unacceptable HTTP version; accept 1.0 or 1.1",
+                ))
+            }
+        };
+        self.method = method.parse()?;
+        self.target = target.to_owned();
+        Ok(())
+    }
+
+    pub fn method(&self) -> Method {
+        self.method
+    }
+
+    /// Request-target as sent, usually origin-form such as `/path?query`.
+    pub fn target(&self) -> &str {
+        &self.target
+    }
+
+    pub fn request_headers(&self) -> &HeaderMap {
+        &self.request_headers
+    }
+
+    /// Whether the request line says HTTP/1.0 rather than HTTP/1.1.
+    pub fn is_http_1_0(&self) -> bool {
+        self.http_1_0
+    }
+
+    /// Trailer fields that came after the last chunk of the request body.
+    pub fn request_trailer_iter(&self) -> HeaderIter<'_> {
+        HeaderIter::new(self.decoder.trailers())
+    }
+
+    /// Reads the request body, 0 at its end.
+    ///
+    /// Sends `100 Continue` first when the client waits for it.
+    pub async fn request_body_read(&mut self, buf: &mut [u8]) -> anyhow::Result<usize> {
+        if !self.decoder.has_body() {
+            return Ok(0);
+        }
+        if self.expect_continue {
+            self.expect_continue = false;
+            if self.response == Response::Pending {
+                self.buffer
+                    .write_str("HTTP/1.1 100 Continue\r\n\r\n")
+                    .await
+                    .context("send 100 Continue")?;
+            }
+        }
+        self.decoder
+            .read(&mut self.buffer, buf, &self.limits, self.strictness)
+            .await
+    }
+
+    /// Reads the rest of the request body, at most `Limits::max_buffered_body` bytes.
+    pub async fn request_bytes(&mut self) -> anyhow::Result<Vec<u8>> {
+        let limit = self.limits.max_buffered_body;
+        if self
+            .decoder
+            .content_length()
+            .is_some_and(|length| length > limit)
+        {
+            return Err(LimitError::BodyTooLarge.into());
+        }
+        let mut body = vec![];
+        let mut buf = [0; 4096];
+        loop {
+            let n = self.request_body_read(&mut buf).await?;
+            if n == 0 {
+                return Ok(body);
+            }
+            if body.len() + n > limit {
+                return Err(LimitError::BodyTooLarge.into());
+            }
+            body.extend_from_slice(&buf[..n]);
+        }
+    }
+}
+
+impl<S: Socket> ServerConnection<S> {
+    /// Hands the socket over for a response written by hand.
+    #[cfg(feature = "test-util")]
+    pub(crate) fn raw_response(&mut self) -> anyhow::Result<&mut S> {
+        if self.response != Response::Pending {
+            return Err(anyhow::Error::msg("no request is waiting for a response"));
+        }
+        self.response = Response::Complete;
+        Ok(self.buffer.get_mut())
+    }
+
+    /// Sends the status line of the final response, the reason phrase may be empty
+    /// but cannot contain control characters other than HTAB.
+    ///
+    /// The code must be 200 or above: `100 Continue` is sent by [`ServerConnection::request_body_read`],
+    /// and `101 Switching Protocols` is not supported since the connection cannot be handed over.
+    pub async fn response_begin(&mut self, code: u16, reason: &str) -> anyhow::Result<()> {
+        if self.response != Response::Pending {
+            return Err(anyhow::Error::msg("no request is waiting for a response"));
+        }
+        if !(200..1000).contains(&code) {
+            return Err(anyhow::Error::msg(format!(
+                "unsupported response status {}",
+                code
+            )));
+        }
+        // reason-phrase = 1*( HTAB / SP / VCHAR / obs-text ), RFC 9112 §4.
+        if !reason
+            .bytes()
+            .all(|b| b == b'\t' || b == b' ' || b.is_ascii_graphic() || b >= 0x80)
+        {
+            return Err(anyhow::Error::msg(format!(
+                "invalid response reason phrase {:?}",
+                reason
+            )));
+        }
+        self.buffer
+            .write_str(&format!("HTTP/1.1 {} {}\r\n", code, reason))
+            .await
+            .context("send status line")?;
+        self.response = Response::Head { code };
+        Ok(())
+    }
+
+    /// Fields are checked before anything is written. A failure after the status line
+    /// ends the response and the connection, which closes once the error is handled.
+    pub async fn response_header(&mut self, header: HttpHeader) -> anyhow::Result<()> {
+        self.check_response_head()?;
+        let field = header.to_string();
+        let (name, value) = field.split_once(':').unwrap_or((&field, ""));
+        let value = trim_ows(value);
+        let result = match check_field(name, value.as_bytes())
+            .and_then(|_| self.note_response_field(name, value))
+        {
+            Ok(()) => self
+                .buffer
+                .write_str(&format!("{}\r\n", field))
+                .await
+                .context("send response header"),
+            Err(e) => Err(e),
+        };
+        result.inspect_err(|_| self.abort_response())
+    }
+
+    /// Sends all the headers with a single write, see [`ServerConnection::response_header`].
+    pub async fn response_headers(&mut self, headers: &HeaderMap) -> anyhow::Result<()> {
+        self.check_response_head()?;
+        let checked = headers.to_bytes().and_then(|bytes| {
+            for (name, value) in headers.iter() {
+                self.note_response_field(name, &String::from_utf8_lossy(value))?;
+            }
+            Ok(bytes)
+        });
+        let result = match checked {
+            Ok(bytes) => self
+                .buffer
+                .write_bytes(bytes)
+                .await
+                .context("send response headers"),
+            Err(e) => Err(e),
+        };
+        result.inspect_err(|_| self.abort_response())
+    }
+
+    fn check_response_head(&self) -> anyhow::Result<()> {
+        match self.response {
+            Response::Head { .. } => Ok(()),
+            _ => Err(anyhow::Error::msg(
+                "response header outside of the response header section",
+            )),
+        }
+    }
+
+    /// A response that cannot be finished leaves the connection out of sync with the client.
+    fn abort_response(&mut self) {
+        self.keep_alive = false;
+        self.response = Response::Complete;
+    }
+
+    fn note_response_field(&mut self, name: &str, value: &str) -> anyhow::Result<()> {
+        let transfer_encoding = name.eq_ignore_ascii_case("transfer-encoding");
+        if transfer_encoding && self.http_1_0 {
+            return Err(anyhow::Error::msg(
+                "response Transfer-Encoding cannot be sent to an HTTP/1.0 client",
+            ));
+        }
+        let content_length = name.eq_ignore_ascii_case("content-length");
+        let conflict = match self.response_framing {
+            Some(Framing::ContentLength(_)) => transfer_encoding,
+            Some(_) => content_length,
+            None => false,
+        };
+        if conflict {
+            return Err(anyhow::Error::msg(
+                "response has both Content-Length and Transfer-Encoding",
+            ));
+        }
+        if content_length {
+            let length = value
+                .trim()
+                .parse()
+                .context("response Content-Length is not a number")?;
+            self.response_framing = Some(Framing::ContentLength(length));
+        } else if transfer_encoding {
+            let chunked = value
+                .rsplit(',')
+                .next()
+                .is_some_and(|coding| coding.trim().eq_ignore_ascii_case("chunked"));
+            self.response_framing = Some(if chunked {
+                Framing::Chunked
+            } else {
+                Framing::UntilClose
+            });
+        } else if name.eq_ignore_ascii_case("connection")
+            && value
+                .split(',')
+                .any(|token| token.trim().eq_ignore_ascii_case("close"))
+        {
+            self.keep_alive = false;
+            self.close_sent = true;
+        }
+        Ok(())
+    }
+
+    /// Ends the header section and settles the framing of the response body.
+    ///
+    /// Without Content-Length or Transfer-Encoding the body is chunked,
+    /// or delimited by closing the connection for an HTTP/1.0 client.
+    pub async fn response_headers_end(&mut self) -> anyhow::Result<()> {
+        let Response::Head { code } = self.response else {
+            return Err(anyhow::Error::msg("response status line is not sent"));
+        };
+        let mut fields = String::new();
+        let framing = if self.method == Method::Head || code == 204 || code == 304 {
+            Framing::NoBody
+        } else {
+            match self.response_framing {
+                Some(framing) => framing,
+                None if self.http_1_0 => Framing::UntilClose,
+                None => {
+                    fields.push_str("Transfer-Encoding: chunked\r\n");
+                    Framing::Chunked
+                }
+            }
+        };
+        // The client may never send a body it was not asked to continue with.
+        if framing == Framing::UntilClose || (self.expect_continue && self.decoder.has_body()) {
+            self.keep_alive = false;
+        }
+        if !self.keep_alive && !self.close_sent {
+            fields.push_str("Connection: close\r\n");
+        } else if self.keep_alive && self.http_1_0 {
+            fields.push_str("Connection: keep-alive\r\n");
+        }
+        fields.push_str("\r\n");
+        self.buffer
+            .write_str(&fields)
+            .await
+            .context("end of response headers")?;
+        self.response = Response::Body(framing);
+        Ok(())
+    }
+
+    /// Sends a piece of the response body, as a chunk when the body is chunked.
+    ///
+    /// Discarded when the response has no body, such as the one to HEAD.
+    pub async fn response_body_chunk(&mut self, data: impl AsRef<[u8]>) -> anyhow::Result<()> {
+        let data = data.as_ref();
+        let Response::Body(framing) = self.response else {
+            return Err(anyhow::Error::msg("response headers are not finished"));
+        };
+        match framing {
+            Framing::NoBody => return Ok(()),
+            Framing::ContentLength(length) if self.response_written + data.len() > length => {
+                return Err(anyhow::Error::msg(
+                    "response body is longer than its Content-Length",
+                ))
+            }
+            Framing::Chunked if !data.is_empty() => {
+                let mut chunk = format!("{:x}\r\n", data.len()).into_bytes();
+                chunk.extend_from_slice(data);
+                chunk.extend_from_slice(b"\r\n");
+                self.buffer
+                    .write_bytes(chunk)
+                    .await
+                    .context("send response chunk")?;
+            }
+            Framing::Chunked => {}
+            Framing::ContentLength(_) | Framing::UntilClose => self
+                .buffer
+                .write_bytes(data)
+                .await
+                .context("send response body")?,
+        }
+        self.response_written += data.len();
+        Ok(())
+    }
+
+    /// Sends the last chunk of a chunked body, or checks that the Content-Length was met.
+    pub async fn response_end(&mut self) -> anyhow::Result<()> {
+        let Response::Body(framing) = self.response else {
+            return Err(anyhow::Error::msg("response headers are not finished"));
+        };
+        match framing {
+            Framing::Chunked => self
+                .buffer
+                .write_str("0\r\n\r\n")
+                .await
+                .context("send last response chunk")?,
+            Framing::ContentLength(length) if self.response_written < length => {
+                return Err(anyhow::Error::msg(
+                    "response body is shorter than its Content-Length",
+                ))
+            }
+            _ => {}
+        }
+        self.response = Response::Complete;
+        Ok(())
+    }
+
+    /// Sends a whole response, with a Content-Length unless `headers` frame it already.
+    pub async fn respond(
+        &mut self,
+        code: u16,
+        reason: &str,
+        headers: &HeaderMap,
+        body: impl AsRef<[u8]>,
+    ) -> anyhow::Result<()> {
+        let body = body.as_ref();
+        self.response_begin(code, reason).await?;
+        self.response_headers(headers).await?;
+        if self.response_framing.is_none() && code != 204 && code != 304 {
+            self.response_header(HttpHeader::ContentLength(body.len()))
+                .await?;
+        }
+        self.response_headers_end().await?;
+        self.response_body_chunk(body).await?;
+        self.response_end().await
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test_util::MockSocket;
+    use crate::MediaType;
+
+    #[tokio::test]
+    async fn try_bad_response_field() {
+        let socket = MockSocket::new()
+            .then_read("GET / HTTP/1.1\r\nHost: x\r\n\r\nGET /next HTTP/1.1\r\nHost: x\r\n\r\n");
+        let writes = socket.writes();
+        let mut connection = ServerConnection::new(socket);
+        assert!(connection.next_request().await.unwrap());
+        assert!(connection
+            .response_header(HttpHeader::ContentLength(1))
+            .await
+            .is_err());
+        connection.response_begin(200, "OK").await.unwrap();
+        let mut headers = HeaderMap::new();
+        headers.append("Connection", "keep-alive").unwrap();
+        headers.append("Content-Length", "many").unwrap();
+        assert!(connection.response_headers(&headers).await.is_err());
+        assert!(connection.response_headers_end().await.is_err());
+        assert!(!connection.next_request().await.unwrap());
+        assert_eq!(b"HTTP/1.1 200 OK\r\n", writes.bytes().as_slice());
+    }
+
+    #[tokio::test]
+    async fn try_bad_reason_phrase() {
+        let socket = MockSocket::new().then_read("GET / HTTP/1.1\r\nHost: x\r\n\r\n");
+        let writes = socket.writes();
+        let mut connection = ServerConnection::new(socket);
+        assert!(connection.next_request().await.unwrap());
+        assert!(connection
+            .response_begin(200, "OK\r\nSet-Cookie: a=b")
+            .await
+            .is_err());
+        assert!(writes.bytes().is_empty());
+        connection.response_begin(200, "Très\tbien").await.unwrap();
+        assert_eq!(
+            "HTTP/1.1 200 Très\tbien\r\n",
+            String::from_utf8(writes.bytes()).unwrap()
+        );
+    }
+
+    #[tokio::test]
+    async fn try_conflicting_framing() {
+        let socket = MockSocket::new()
+            .then_read("GET / HTTP/1.1\r\nHost: x\r\n\r\nGET / HTTP/1.1\r\nHost: x\r\n\r\n");
+        let mut connection = ServerConnection::new(socket);
+        assert!(connection.next_request().await.unwrap());
+        connection.response_begin(200, "OK").await.unwrap();
+        let mut headers = HeaderMap::new();
+        headers.append("Transfer-Encoding", "chunked").unwrap();
+        headers.append("Content-Length", "1").unwrap();
+        assert!(connection.response_headers(&headers).await.is_err());
+        assert!(!connection.next_request().await.unwrap());
+
+        let socket = MockSocket::new().then_read("GET / HTTP/1.0\r\n\r\n");
+        let writes = socket.writes();
+        let mut connection = ServerConnection::new(socket);
+        assert!(connection.next_request().await.unwrap());
+        connection.response_begin(200, "OK").await.unwrap();
+        let mut headers = HeaderMap::new();
+        headers.append("Transfer-Encoding", "chunked").unwrap();
+        assert!(connection.response_headers(&headers).await.is_err());
+        assert_eq!(b"HTTP/1.1 200 OK\r\n", writes.bytes().as_slice());
+    }
+
+    #[tokio::test]
+    async fn try_missing_host() {
+        let socket = MockSocket::new().then_read("GET / HTTP/1.1\r\n\r\nGET / HTTP/1.1\r\n\r\n");
+        let writes = socket.writes();
+        let mut connection = ServerConnection::new(socket);
+        assert!(connection.next_request().await.is_err());
+        assert!(!connection.next_request().await.unwrap());
+        assert_eq!(
+            "HTTP/1.1 400 Bad Request\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+            String::from_utf8(writes.bytes()).unwrap()
+        );
+    }
+
+    #[tokio::test]
+    async fn try_serve_pipelined() {
+        let socket = MockSocket::new().then_read(
+            b"POST /echo HTTP/1.1\r\nHost: x\r\nExpect: 100-continue\r\nTransfer-Encoding: chunked\r\n\r\n\
+              5\r\nhello\r\n0\r\n\r\n\
+              GET /a HTTP/1.1\r\nHost: x\r\nContent-Length: 3\r\n\r\nabc\
+              \r\nGET /b HTTP/1.0\r\n\r\n",
+        );
+        let writes = socket.writes();
+        let mut connection = ServerConnection::new(socket);
+        assert!(connection.next_request().await.unwrap());
+        assert_eq!(
+            (Method::Post, "/echo"),
+            (connection.method(), connection.target())
+        );
+        assert_eq!(
+            b"hello",
+            connection.request_bytes().await.unwrap().as_slice()
+        );
+        connection.response_begin(200, "OK").await.unwrap();
+        connection
+            .response_header(HttpHeader::ContentType(MediaType::text_plain()))
+            .await
+            .unwrap();
+        connection.response_headers_end().await.unwrap();
+        connection.response_body_chunk("hel").await.unwrap();
+        connection.response_body_chunk("lo").await.unwrap();
+        connection.response_end().await.unwrap();
+
+        assert!(connection.next_request().await.unwrap());
+        assert_eq!("/a", connection.target());
+        connection
+            .respond(200, "OK", &HeaderMap::new(), "a")
+            .await
+            .unwrap();
+
+        assert!(connection.next_request().await.unwrap());
+        assert_eq!("/b", connection.target());
+        connection
+            .respond(404, "Not Found", &HeaderMap::new(), "")
+            .await
+            .unwrap();
+        assert!(!connection.next_request().await.unwrap());
+
+        let output = String::from_utf8(writes.bytes()).unwrap();
+        assert_eq!(
+            "HTTP/1.1 100 Continue\r\n\r\n\
+             HTTP/1.1 200 OK\r\nContent-Type: text/plain; charset=utf-8\r\nTransfer-Encoding: chunked\r\n\r\n\
+             3\r\nhel\r\n2\r\nlo\r\n0\r\n\r\n\
+             HTTP/1.1 200 OK\r\nContent-Length: 1\r\n\r\na\
+             HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+            output
+        );
+    }
+
+    #[tokio::test]
+    async fn try_client_round_trip() {
+        let (client, server) = tokio::io::duplex(4096);
+        let serve = tokio::spawn(async move {
+            let mut connection = ServerConnection::new(server);
+            while connection.next_request().await.unwrap() {
+                let body = connection.request_bytes().await.unwrap();
+                connection
+                    .respond(200, "OK", &HeaderMap::new(), body)
+                    .await
+                    .unwrap();
+            }
+        });
+
+        let url = url::Url::parse("http://example.org/").unwrap();
+        let mut http = crate::http::Context::from_socket(url, client);
+        for body in ["first", "second"] {
+            http.begin_request(Method::Post).await.unwrap();
+            http.request_header(HttpHeader::ContentLength(body.len()))
+                .await
+                .unwrap();
+            http.request_headers_end().await.unwrap();
+            http.request_body_chunk(body).await.unwrap();
+            http.response_begin().await.unwrap();
+            assert_eq!(body.as_bytes(), http.bytes().await.unwrap().as_slice());
+        }
+        drop(http);
+        serve.await.unwrap();
+    }
+}
@@ -0,0 +1,438 @@
+//! Server-Sent Events, see https://html.spec.whatwg.org/multipage/server-sent-events.html
+
+use super::limits::{LimitError, Limits};
+use super::{BodyReader, Context};
+use crate::connector::{Connector, TcpConnector};
+use crate::{HttpHeader, Method, Socket};
+use anyhow::Context as AnyHowContext;
+use std::collections::VecDeque;
+use std::time::Duration;
+
+/// Reconnection delay until the server sends a `retry` field.
+const DEFAULT_RETRY: Duration = Duration::from_secs(3);
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Event {
+    /// Last event ID of the stream when the event was dispatched.
+    pub id: Option<String>,
+    /// `message` unless the event has an `event` field.
+    pub event: String,
+    pub data: String,
+    /// Reconnection delay sent together with the event.
+    pub retry: Option<Duration>,
+}
+
+/// Incremental parser of a `text/event-stream` body fed with arbitrary pieces.
+#[derive(Debug, Default)]
+pub struct EventParser {
+    buf: Vec<u8>,
+    bom_checked: bool,
+    last_event_id: String,
+    event_type: String,
+    data: String,
+    block_retry: Option<Duration>,
+    retry: Option<Duration>,
+    events: VecDeque<Event>,
+    limits: Limits,
+}
+
+impl EventParser {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// An incomplete line or event data over `max_buffered_body` fails with
+    /// [`LimitError::BodyTooLarge`].
+    pub fn with_limits(limits: Limits) -> Self {
+        Self {
+            limits,
+            ..Self::default()
+        }
+    }
+
+    pub fn push(&mut self, bytes: &[u8]) -> Result<(), LimitError> {
+        self.buf.extend_from_slice(bytes);
+        if !self.bom_checked {
+            if b"\xef\xbb\xbf".starts_with(&self.buf) {
+                return Ok(());
+            }
+            if self.buf.starts_with(b"\xef\xbb\xbf") {
+                self.buf.drain(..3);
+            }
+            self.bom_checked = true;
+        }
+        let mut start = 0;
+        while let Some(i) = self.buf[start..]
+            .iter()
+            .position(|b| *b == b'\r' || *b == b'\n')
+        {
+            let end = start + i;
+            let next = match (self.buf[end], self.buf.get(end + 1)) {
+                // CR at the end of the input may be the first half of CRLF.
+                (b'\r', None) => break,
+                (b'\r', Some(b'\n')) => end + 2,
+                _ => end + 1,
+            };
+            let line = String::from_utf8_lossy(&self.buf[start..end]).into_owned();
+            self.process_line(&line)?;
+            start = next;
+        }
+        self.buf.drain(..start);
+        if self.buf.len() > self.limits.max_buffered_body {
+            return Err(LimitError::BodyTooLarge);
+        }
+        Ok(())
+    }
+
+    pub fn next_event(&mut self) -> Option<Event> {
+        self.events.pop_front()
+    }
+
+    pub fn last_event_id(&self) -> &str {
+        &self.last_event_id
+    }
+
+    /// Latest reconnection delay sent by the server.
+    pub fn retry(&self) -> Option<Duration> {
+        self.retry
+    }
+
+    /// Drops the incomplete event of a closed stream, the last event ID is kept.
+    fn reset(&mut self) {
+        self.buf.clear();
+        self.bom_checked = false;
+        self.event_type.clear();
+        self.data.clear();
+        self.block_retry = None;
+    }
+
+    fn process_line(&mut self, line: &str) -> Result<(), LimitError> {
+        if line.is_empty() {
+            self.dispatch();
+            return Ok(());
+        }
+        if line.starts_with(':') {
+            return Ok(());
+        }
+        let (field, value) = line.split_once(':').unwrap_or((line, ""));
+        let value = value.strip_prefix(' ').unwrap_or(value);
+        match field {
+            "event" => self.event_type = value.to_owned(),
+            "data" => {
+                if self.data.len() + value.len() >= self.limits.max_buffered_body {
+                    return Err(LimitError::BodyTooLarge);
+                }
+                self.data.push_str(value);
+                self.data.push('\n');
+            }
+            "id" if !value.contains('\0') => self.last_event_id = value.to_owned(),
+            "retry" if !value.is_empty() && value.bytes().all(|b| b.is_ascii_digit()) => {
+                if let Ok(millis) = value.parse() {
+                    self.retry = Some(Duration::from_millis(millis));
+                    self.block_retry = self.retry;
+                }
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    fn dispatch(&mut self) {
+        let event_type = std::mem::take(&mut self.event_type);
+        let mut data = std::mem::take(&mut self.data);
+        let retry = self.block_retry.take();
+        if data.is_empty() {
+            return;
+        }
+        data.pop();
+        self.events.push_back(Event {
+            id: (!self.last_event_id.is_empty()).then(|| self.last_event_id.clone()),
+            event: if event_type.is_empty() {
+                "message".to_owned()
+            } else {
+                event_type
+            },
+            data,
+            retry,
+        });
+    }
+}
+
+/// Events of a single response body.
+#[derive(Debug)]
+pub struct EventStream<'a, S: Socket> {
+    body: BodyReader<'a, S>,
+    parser: EventParser,
+}
+
+impl<'a, S: Socket> EventStream<'a, S> {
+    pub fn new(body: BodyReader<'a, S>) -> Self {
+        Self {
+            body,
+            parser: EventParser::new(),
+        }
+    }
+
+    /// Returns `None` at the end of the body.
+    pub async fn next_event(&mut self) -> anyhow::Result<Option<Event>> {
+        let mut buf = [0; 4096];
+        loop {
+            if let Some(event) = self.parser.next_event() {
+                return Ok(Some(event));
+            }
+            let n = self.body.read(&mut buf).await?;
+            if n == 0 {
+                return Ok(None);
+            }
+            self.parser.push(&buf[..n])?;
+        }
+    }
+
+    pub fn parser(&self) -> &EventParser {
+        &self.parser
+    }
+}
+
+impl<S: Socket> Context<S> {
+    /// The event parser gets the limits of the context.
+    pub fn events(&mut self) -> EventStream<'_, S> {
+        let parser = EventParser::with_limits(self.limits());
+        EventStream {
+            body: self.body(),
+            parser,
+        }
+    }
+}
+
+/// Event stream that reconnects with `Last-Event-ID` when the connection ends.
+#[derive(Debug)]
+pub struct EventSource<C: Connector = TcpConnector> {
+    url: url::Url,
+    connector: C,
+    context: Option<Context<C::Socket>>,
+    parser: EventParser,
+    reconnect: bool,
+    closed: bool,
+}
+
+impl EventSource {
+    pub fn new(url: impl AsRef<str>) -> anyhow::Result<Self> {
+        Self::with_connector(url, TcpConnector::new())
+    }
+}
+
+impl<C: Connector> EventSource<C> {
+    pub fn with_connector(url: impl AsRef<str>, connector: C) -> anyhow::Result<Self> {
+        Ok(Self {
+            url: url::Url::parse(url.as_ref()).context("parse URL")?,
+            connector,
+            context: None,
+            parser: EventParser::new(),
+            reconnect: false,
+            closed: false,
+        })
+    }
+
+    /// Applies to every connection and to the event parser, see [`EventParser::with_limits`].
+    pub fn set_limits(&mut self, limits: Limits) {
+        self.parser.limits = limits;
+        if let Some(context) = &mut self.context {
+            context.set_limits(limits);
+        }
+    }
+
+    pub fn last_event_id(&self) -> &str {
+        self.parser.last_event_id()
+    }
+
+    /// Delay before reconnecting, 3 seconds until the server sends `retry`.
+    pub fn retry(&self) -> Duration {
+        self.parser.retry().unwrap_or(DEFAULT_RETRY)
+    }
+
+    /// Returns `None` once the server answers with 204 No Content.
+    ///
+    /// The stream reconnects when the body ends or the connection fails. Other errors,
+    /// like a failed reconnection, an exceeded limit or a truncated body, are returned
+    /// and the next call reconnects.
+    pub async fn next_event(&mut self) -> anyhow::Result<Option<Event>> {
+        let mut buf = [0; 4096];
+        loop {
+            if let Some(event) = self.parser.next_event() {
+                return Ok(Some(event));
+            }
+            if self.closed {
+                return Ok(None);
+            }
+            let context = match &mut self.context {
+                Some(context) => context,
+                None => {
+                    if self.reconnect {
+                        tokio::time::sleep(self.retry()).await;
+                    }
+                    self.reconnect = true;
+                    match self.connect().await? {
+                        Some(context) => self.context.insert(context),
+                        None => {
+                            self.closed = true;
+                            return Ok(None);
+                        }
+                    }
+                }
+            };
+            let result = match context.body().read(&mut buf).await {
+                Ok(0) => Ok(()),
+                Ok(n) => match self.parser.push(&buf[..n]) {
+                    Ok(()) => continue,
+                    Err(e) => Err(e.into()),
+                },
+                Err(e) if e.chain().any(|cause| cause.is::<std::io::Error>()) => Ok(()),
+                Err(e) => Err(e),
+            };
+            self.context = None;
+            self.parser.reset();
+            result?;
+        }
+    }
+
+    async fn connect(&self) -> anyhow::Result<Option<Context<C::Socket>>> {
+        let mut context = Context::with_connector(self.url.as_str(), &self.connector).await?;
+        context.set_limits(self.parser.limits);
+        context.begin_request(Method::Get).await?;
+        context
+            .request_header(HttpHeader::Accept(vec!["text/event-stream".to_owned()]))
+            .await?;
+        context
+            .request_header(HttpHeader::CacheControl(vec!["no-cache".to_owned()]))
+            .await?;
+        if !self.last_event_id().is_empty() {
+            context
+                .request_header(HttpHeader::Custom {
+                    name: "Last-Event-ID".to_owned(),
+                    value: self.last_event_id().to_owned(),
+                })
+                .await?;
+        }
+        context.request_headers_end().await?;
+        context.end_request();
+        context.response_begin().await?;
+        match context.status()?.code {
+            200 => {}
+            204 => return Ok(None),
+            code => {
+                return Err(anyhow::Error::msg(format!(
+                    "event stream answered with status {}",
+                    code
+                )))
+            }
+        }
+        let media_type = context.response_media_type()?;
+        if media_type.map(|media_type| media_type.essence()).as_deref() != Some("text/event-stream")
+        {
+            return Err(anyhow::Error::msg("response is not text/event-stream"));
+        }
+        Ok(Some(context))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::http::framing::TruncatedBody;
+    use crate::test_util::{MockResponse, MockServer};
+
+    #[test]
+    fn try_parse_events() {
+        let mut parser = EventParser::new();
+        for piece in [
+            &b"\xef\xbb"[..],
+            b"\xbfretry: 10\r",
+            b"\n: comment\r\nid: 1\ndata: first\r",
+            b"data:  line\r\revent: update\nda",
+            b"ta\n\n",
+        ] {
+            parser.push(piece).unwrap();
+        }
+        assert_eq!(
+            Some(Event {
+                id: Some("1".to_owned()),
+                event: "message".to_owned(),
+                data: "first\n line".to_owned(),
+                retry: Some(Duration::from_millis(10)),
+            }),
+            parser.next_event()
+        );
+        assert_eq!(
+            Some(Event {
+                id: Some("1".to_owned()),
+                event: "update".to_owned(),
+                data: String::new(),
+                retry: None,
+            }),
+            parser.next_event()
+        );
+        assert_eq!(None, parser.next_event());
+
+        let limits = Limits {
+            max_buffered_body: 8,
+            ..Limits::default()
+        };
+        let mut parser = EventParser::with_limits(limits);
+        parser.push(b"data: 0123\ndata: 4\n\n").unwrap();
+        assert_eq!(
+            Err(LimitError::BodyTooLarge),
+            parser.push(b"data: 0123\ndata: 456\n")
+        );
+        let mut parser = EventParser::with_limits(limits);
+        assert_eq!(Err(LimitError::BodyTooLarge), parser.push(b": 012345678"));
+    }
+
+    fn event_stream() -> MockResponse {
+        MockResponse::new(200, "OK").header("Content-Type", "text/event-stream")
+    }
+
+    #[tokio::test]
+    async fn try_reconnect() {
+        let server = MockServer::start().await.unwrap();
+        server.enqueue(
+            event_stream()
+                .chunked()
+                .chunk("retry: 5\r")
+                .chunk("\n\nid: 7\nda")
+                .chunk("ta: a\n\nda")
+                .last_chunk(&[]),
+        );
+        server.enqueue(event_stream().raw("\r\ndata: b\n\n").close());
+        server.enqueue(MockResponse::new(204, "No Content").raw("\r\n"));
+
+        let mut events = EventSource::new(server.url("/feed")).unwrap();
+        let event = events.next_event().await.unwrap().unwrap();
+        assert_eq!(("a", Some("7")), (event.data.as_str(), event.id.as_deref()));
+        assert_eq!(Duration::from_millis(5), events.retry());
+        let event = events.next_event().await.unwrap().unwrap();
+        assert_eq!(("b", Some("7")), (event.data.as_str(), event.id.as_deref()));
+        assert_eq!(None, events.next_event().await.unwrap());
+
+        let requests = server.requests();
+        assert_eq!(None, requests[0].headers.get("last-event-id"));
+        assert_eq!(Some("7"), requests[1].headers.get_str("last-event-id"));
+    }
+
+    #[tokio::test]
+    async fn try_truncated_stream() {
+        let server = MockServer::start().await.unwrap();
+        server.enqueue(
+            event_stream()
+                .header("Content-Length", "100")
+                .raw("\r\nretry: 1\ndata: a\n\n")
+                .close(),
+        );
+        server.enqueue(MockResponse::new(204, "No Content").raw("\r\n"));
+
+        let mut events = EventSource::new(server.url("/feed")).unwrap();
+        assert_eq!("a", events.next_event().await.unwrap().unwrap().data);
+        let e = events.next_event().await.unwrap_err();
+        assert!(e.downcast_ref::<TruncatedBody>().is_some());
+        assert_eq!(None, events.next_event().await.unwrap());
+    }
+}
@@ -0,0 +1,103 @@
+//! Decoding of text bodies according to the charset of the Content-Type.
+
+use super::{Context, MediaType};
+use crate::Socket;
+use encoding_rs::{CoderResult, Encoding, UTF_8};
+
+/// Incremental decoder that keeps multibyte sequences split between reads.
+///
+/// A byte order mark overrides the declared charset and is removed.
+pub struct TextDecoder {
+    decoder: encoding_rs::Decoder,
+}
+
+impl TextDecoder {
+    pub fn new(encoding: &'static Encoding) -> Self {
+        Self {
+            decoder: encoding.new_decoder(),
+        }
+    }
+
+    /// Decoder for the charset parameter, UTF-8 when it is absent or unknown.
+    pub fn for_media_type(media_type: Option<&MediaType>) -> Self {
+        let encoding = media_type
+            .and_then(MediaType::charset)
+            .and_then(|charset| Encoding::for_label(charset.as_bytes()))
+            .unwrap_or(UTF_8);
+        Self::new(encoding)
+    }
+
+    pub fn encoding(&self) -> &'static Encoding {
+        self.decoder.encoding()
+    }
+
+    /// Decodes the next bytes, `last` flushes an incomplete trailing sequence.
+    ///
+    /// Malformed sequences become U+FFFD.
+    pub fn decode(&mut self, bytes: &[u8], last: bool) -> String {
+        let capacity = self
+            .decoder
+            .max_utf8_buffer_length(bytes.len())
+            .unwrap_or(bytes.len() * 3 + 16);
+        let mut text = String::with_capacity(capacity);
+        let (result, _, _) = self.decoder.decode_to_string(bytes, &mut text, last);
+        debug_assert_eq!(CoderResult::InputEmpty, result);
+        text
+    }
+}
+
+impl std::fmt::Debug for TextDecoder {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("TextDecoder")
+            .field("encoding", &self.encoding().name())
+            .finish()
+    }
+}
+
+impl<S: Socket> Context<S> {
+    /// Decoder for the response body, a malformed Content-Type falls back to UTF-8.
+    pub fn text_decoder(&self) -> TextDecoder {
+        TextDecoder::for_media_type(self.response_media_type().ok().flatten().as_ref())
+    }
+
+    /// Reads the rest of the response body like [`Context::bytes`] and decodes it.
+    pub async fn text(&mut self) -> anyhow::Result<String> {
+        let mut decoder = self.text_decoder();
+        let body = self.bytes().await?;
+        Ok(decoder.decode(&body, true))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use tokio::io::AsyncWriteExt;
+
+    #[test]
+    fn try_split_multibyte() {
+        let mut decoder = TextDecoder::for_media_type(None);
+        let bytes = "привет".as_bytes();
+        let mut text = decoder.decode(&bytes[..3], false);
+        text += &decoder.decode(&bytes[3..], false);
+        text += &decoder.decode(b"\xd0", true);
+        assert_eq!("привет\u{fffd}", text);
+
+        let media_type = "text/plain; charset=latin1".parse().unwrap();
+        let mut decoder = TextDecoder::for_media_type(Some(&media_type));
+        assert_eq!("windows-1252", decoder.encoding().name());
+        assert_eq!("ok", decoder.decode(b"\xef\xbb\xbfok", true));
+    }
+
+    #[tokio::test]
+    async fn try_text() {
+        let (client, mut server) = tokio::io::duplex(4096);
+        server
+            .write_all(b"HTTP/1.1 200 OK\r\nContent-Type: text/plain; charset=windows-1251\r\nTransfer-Encoding: chunked\r\n\r\n3\r\n\xef\xf0\xe8\r\n3\r\n\xe2\xe5\xf2\r\n0\r\n\r\n")
+            .await
+            .unwrap();
+        let url = url::Url::parse("http://example.org/").unwrap();
+        let mut http = Context::from_socket(url, client);
+        http.response_begin().await.unwrap();
+        assert_eq!("привет", http.text().await.unwrap());
+    }
+}
@@ -1,6 +1,16 @@
 mod bbuf;
+#[cfg(feature = "tower")]
+pub mod client;
+pub mod connector;
 pub mod http;
+pub mod proxy;
 mod socket;
+#[cfg(feature = "test-util")]
+pub mod test_util;
+#[cfg(feature = "websocket")]
+pub mod websocket;
 
-pub use http::{headers::HttpHeader, method::Method};
-pub use socket::Socket;
+pub use connector::{Connector, TcpConnector};
+pub use http::{headers::HttpHeader, method::Method, HeaderMap, MediaType};
+pub use proxy::{Proxy, Socks5Proxy};
+pub use socket::{Rewind, Socket};
@@ -0,0 +1,7 @@
+//! Test support for code built on [`crate::http::Context`], without a network.
+
+mod server;
+mod socket;
+
+pub use server::{MockResponse, MockServer, RecordedRequest};
+pub use socket::{MockSocket, WriteLog};
@@ -0,0 +1,240 @@
+//! Local HTTP/1.1 server playing scripted responses.
+
+use crate::http::ServerConnection;
+use crate::{HeaderMap, Method};
+use anyhow::Context as AnyHowContext;
+use std::collections::VecDeque;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+use std::time::Duration;
+use tokio::io::AsyncWriteExt;
+use tokio::net::{TcpListener, TcpStream};
+use tokio::task::JoinHandle;
+
+#[derive(Debug, Clone)]
+enum Action {
+    Write(Vec<u8>),
+    Pause(Duration),
+    Close,
+}
+
+/// Response script sent byte for byte, nothing is added or checked.
+///
+/// Consecutive pieces go out with a single write until [`MockResponse::flush`]
+/// or [`MockResponse::pause`], so a test controls where the client sees the splits.
+#[derive(Debug, Clone, Default)]
+pub struct MockResponse {
+    actions: Vec<Action>,
+    pending: Vec<u8>,
+    byte_delay: Option<Duration>,
+}
+
+impl MockResponse {
+    /// Starts with the status line.
+    pub fn new(code: u16, reason: &str) -> Self {
+        Self::default().raw(format!("HTTP/1.1 {} {}\r\n", code, reason))
+    }
+
+    /// Complete response with a Content-Length body.
+    pub fn with_body(code: u16, reason: &str, body: impl AsRef<[u8]>) -> Self {
+        Self::new(code, reason).body(body)
+    }
+
+    pub fn raw(mut self, bytes: impl AsRef<[u8]>) -> Self {
+        self.pending.extend_from_slice(bytes.as_ref());
+        self
+    }
+
+    pub fn header(self, name: &str, value: &str) -> Self {
+        self.raw(format!("{}: {}\r\n", name, value))
+    }
+
+    /// Content-Length, the end of the header section and the body.
+    pub fn body(self, body: impl AsRef<[u8]>) -> Self {
+        let body = body.as_ref();
+        self.header("Content-Length", &body.len().to_string())
+            .raw("\r\n")
+            .raw(body)
+    }
+
+    /// `Transfer-Encoding: chunked` and the end of the header section.
+    pub fn chunked(self) -> Self {
+        self.header("Transfer-Encoding", "chunked").raw("\r\n")
+    }
+
+    pub fn chunk(self, data: impl AsRef<[u8]>) -> Self {
+        self.chunk_with_extension(data, "")
+    }
+
+    /// Chunk with `extension`, such as `name=value`, after its size.
+    pub fn chunk_with_extension(self, data: impl AsRef<[u8]>, extension: &str) -> Self {
+        let data = data.as_ref();
+        let size = if extension.is_empty() {
+            format!("{:x}\r\n", data.len())
+        } else {
+            format!("{:x};{}\r\n", data.len(), extension)
+        };
+        self.raw(size).raw(data).raw("\r\n")
+    }
+
+    /// Last chunk followed by trailer fields and the end of the body.
+    pub fn last_chunk(mut self, trailers: &[(&str, &str)]) -> Self {
+        self = self.raw("0\r\n");
+        for (name, value) in trailers {
+            self = self.header(name, value);
+        }
+        self.raw("\r\n")
+    }
+
+    /// Ends the current write.
+    pub fn flush(mut self) -> Self {
+        if !self.pending.is_empty() {
+            self.actions
+                .push(Action::Write(std::mem::take(&mut self.pending)));
+        }
+        self
+    }
+
+    /// Ends the current write and waits before the next one.
+    pub fn pause(self, delay: Duration) -> Self {
+        let mut this = self.flush();
+        this.actions.push(Action::Pause(delay));
+        this
+    }
+
+    /// Writes every byte on its own, `delay` apart.
+    pub fn trickle(mut self, delay: Duration) -> Self {
+        self.byte_delay = Some(delay);
+        self
+    }
+
+    /// Closes the connection at this point, whatever the response framing says.
+    pub fn close(self) -> Self {
+        let mut this = self.flush();
+        this.actions.push(Action::Close);
+        this
+    }
+
+    /// Returns `false` when the script closed the connection.
+    async fn play(&self, socket: &mut TcpStream) -> anyhow::Result<bool> {
+        for action in self.clone().flush().actions {
+            match action {
+                Action::Write(bytes) => match self.byte_delay {
+                    Some(delay) => {
+                        for byte in bytes {
+                            socket.write_all(&[byte]).await?;
+                            tokio::time::sleep(delay).await;
+                        }
+                    }
+                    None => socket.write_all(&bytes).await?,
+                },
+                Action::Pause(delay) => tokio::time::sleep(delay).await,
+                Action::Close => return Ok(false),
+            }
+        }
+        Ok(true)
+    }
+}
+
+/// Request as the mock server received it, body included.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RecordedRequest {
+    pub method: Method,
+    pub target: String,
+    pub headers: HeaderMap,
+    pub body: Vec<u8>,
+}
+
+#[derive(Debug, Default)]
+struct Script {
+    responses: VecDeque<MockResponse>,
+    requests: Vec<RecordedRequest>,
+}
+
+/// Server on a local port answering requests with the enqueued responses in order.
+///
+/// A request without a response left gets a 500. The server stops accepting when dropped.
+#[derive(Debug)]
+pub struct MockServer {
+    addr: SocketAddr,
+    script: Arc<Mutex<Script>>,
+    accept: JoinHandle<()>,
+}
+
+impl MockServer {
+    pub async fn start() -> anyhow::Result<Self> {
+        let listener = TcpListener::bind("127.0.0.1:0")
+            .await
+            .context("bind mock server")?;
+        let addr = listener.local_addr()?;
+        let script = Arc::<Mutex<Script>>::default();
+        let accept = tokio::spawn(accept(listener, Arc::clone(&script)));
+        Ok(Self {
+            addr,
+            script,
+            accept,
+        })
+    }
+
+    pub fn addr(&self) -> SocketAddr {
+        self.addr
+    }
+
+    /// URL of `path` on the server, such as `/items?page=2`.
+    pub fn url(&self, path: &str) -> String {
+        format!("http://{}{}", self.addr, path)
+    }
+
+    pub fn enqueue(&self, response: MockResponse) {
+        lock(&self.script).responses.push_back(response);
+    }
+
+    /// Requests received so far, in order.
+    pub fn requests(&self) -> Vec<RecordedRequest> {
+        lock(&self.script).requests.clone()
+    }
+}
+
+impl Drop for MockServer {
+    fn drop(&mut self) {
+        self.accept.abort();
+    }
+}
+
+fn lock(script: &Mutex<Script>) -> MutexGuard<'_, Script> {
+    script.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+async fn accept(listener: TcpListener, script: Arc<Mutex<Script>>) {
+    while let Ok((socket, _)) = listener.accept().await {
+        let script = Arc::clone(&script);
+        tokio::spawn(async move {
+            // A failure ends the connection, the client sees it as such.
+            let _ = serve(socket, &script).await;
+        });
+    }
+}
+
+async fn serve(socket: TcpStream, script: &Mutex<Script>) -> anyhow::Result<()> {
+    let mut connection = ServerConnection::new(socket);
+    while connection.next_request().await? {
+        let request = RecordedRequest {
+            method: connection.method(),
+            target: connection.target().to_owned(),
+            headers: connection.request_headers().clone(),
+            body: connection.request_bytes().await?,
+        };
+        let response = {
+            let mut script = lock(script);
+            script.requests.push(request);
+            script.responses.pop_front()
+        };
+        let response = response.unwrap_or_else(|| {
+            MockResponse::with_body(500, "Internal Server Error", "no scripted response left")
+        });
+        if !response.play(connection.raw_response()?).await? {
+            break;
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,172 @@
+//! In-memory [`crate::Socket`] replaying scripted reads.
+
+use std::collections::VecDeque;
+use std::future::Future;
+use std::io;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex, PoisonError};
+use std::task::{ready, Context, Poll};
+use std::time::Duration;
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+
+#[derive(Debug)]
+enum Step {
+    Data(Vec<u8>),
+    Delay(Duration),
+    Error(io::ErrorKind),
+    Eof,
+}
+
+/// Socket whose reads follow a script step by step.
+///
+/// Every [`MockSocket::then_read`] step is returned by reads of its own, so a test decides
+/// exactly where the bytes are split. Once the script is over reads return EOF.
+#[derive(Debug, Default)]
+pub struct MockSocket {
+    steps: VecDeque<Step>,
+    sleep: Option<Pin<Box<tokio::time::Sleep>>>,
+    writes: WriteLog,
+}
+
+impl MockSocket {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Bytes returned by the next read, or by several when the read buffer is smaller.
+    pub fn then_read(mut self, bytes: impl AsRef<[u8]>) -> Self {
+        self.steps.push_back(Step::Data(bytes.as_ref().to_vec()));
+        self
+    }
+
+    /// The next read waits before going on with the script.
+    pub fn then_delay(mut self, delay: Duration) -> Self {
+        self.steps.push_back(Step::Delay(delay));
+        self
+    }
+
+    pub fn then_error(mut self, kind: io::ErrorKind) -> Self {
+        self.steps.push_back(Step::Error(kind));
+        self
+    }
+
+    /// A single read returns EOF, the script goes on after it.
+    pub fn then_eof(mut self) -> Self {
+        self.steps.push_back(Step::Eof);
+        self
+    }
+
+    /// Handle to the writes, still readable once the socket is moved into a `Context`.
+    pub fn writes(&self) -> WriteLog {
+        self.writes.clone()
+    }
+}
+
+impl AsyncRead for MockSocket {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        loop {
+            if let Some(sleep) = self.sleep.as_mut() {
+                ready!(sleep.as_mut().poll(cx));
+                self.sleep = None;
+            }
+            match self.steps.pop_front() {
+                None | Some(Step::Eof) => return Poll::Ready(Ok(())),
+                Some(Step::Delay(delay)) => self.sleep = Some(Box::pin(tokio::time::sleep(delay))),
+                Some(Step::Error(kind)) => {
+                    return Poll::Ready(Err(io::Error::new(kind, "scripted read error")))
+                }
+                Some(Step::Data(mut data)) => {
+                    let n = data.len().min(buf.remaining());
+                    buf.put_slice(&data[..n]);
+                    if n < data.len() {
+                        data.drain(..n);
+                        self.steps.push_front(Step::Data(data));
+                    }
+                    return Poll::Ready(Ok(()));
+                }
+            }
+        }
+    }
+}
+
+impl AsyncWrite for MockSocket {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        _cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        self.writes.push(buf);
+        Poll::Ready(Ok(buf.len()))
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+}
+
+/// Everything written to a [`MockSocket`], one entry per write call.
+#[derive(Debug, Clone, Default)]
+pub struct WriteLog {
+    writes: Arc<Mutex<Vec<Vec<u8>>>>,
+}
+
+impl WriteLog {
+    fn push(&self, bytes: &[u8]) {
+        self.writes
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .push(bytes.to_vec());
+    }
+
+    pub fn writes(&self) -> Vec<Vec<u8>> {
+        self.writes
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone()
+    }
+
+    /// All the writes joined together.
+    pub fn bytes(&self) -> Vec<u8> {
+        self.writes().concat()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    #[tokio::test]
+    async fn try_mock_socket() {
+        let mut socket = MockSocket::new()
+            .then_read("abc")
+            .then_delay(Duration::from_millis(5))
+            .then_read("de")
+            .then_eof()
+            .then_error(io::ErrorKind::ConnectionReset);
+        let writes = socket.writes();
+
+        let mut buf = [0; 2];
+        assert_eq!(2, socket.read(&mut buf).await.unwrap());
+        assert_eq!(1, socket.read(&mut buf).await.unwrap());
+        assert_eq!(2, socket.read(&mut buf).await.unwrap());
+        assert_eq!(b"de", &buf);
+        assert_eq!(0, socket.read(&mut buf).await.unwrap());
+        let e = socket.read(&mut buf).await.unwrap_err();
+        assert_eq!(io::ErrorKind::ConnectionReset, e.kind());
+        assert_eq!(0, socket.read(&mut buf).await.unwrap());
+
+        socket.write_all(b"GET").await.unwrap();
+        socket.write_all(b" /").await.unwrap();
+        assert_eq!(vec![b"GET".to_vec(), b" /".to_vec()], writes.writes());
+        assert_eq!(b"GET /", writes.bytes().as_slice());
+    }
+}
@@ -0,0 +1,177 @@
+//! Base framing protocol of RFC 6455 §5.2.
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum OpCode {
+    Continuation,
+    Text,
+    Binary,
+    Close,
+    Ping,
+    Pong,
+}
+
+impl OpCode {
+    fn from_bits(bits: u8) -> anyhow::Result<Self> {
+        match bits {
+            0x0 => Ok(Self::Continuation),
+            0x1 => Ok(Self::Text),
+            0x2 => Ok(Self::Binary),
+            0x8 => Ok(Self::Close),
+            0x9 => Ok(Self::Ping),
+            0xA => Ok(Self::Pong),
+            _ => Err(anyhow::Error::msg(format!(
+                "reserved WebSocket opcode {:#x}",
+                bits
+            ))),
+        }
+    }
+
+    fn bits(self) -> u8 {
+        match self {
+            Self::Continuation => 0x0,
+            Self::Text => 0x1,
+            Self::Binary => 0x2,
+            Self::Close => 0x8,
+            Self::Ping => 0x9,
+            Self::Pong => 0xA,
+        }
+    }
+
+    pub(crate) fn is_control(self) -> bool {
+        matches!(self, Self::Close | Self::Ping | Self::Pong)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Frame {
+    pub fin: bool,
+    pub opcode: OpCode,
+    pub payload: Vec<u8>,
+}
+
+impl Frame {
+    pub fn new(opcode: OpCode, payload: impl Into<Vec<u8>>) -> Self {
+        Self {
+            fin: true,
+            opcode,
+            payload: payload.into(),
+        }
+    }
+
+    /// Client frames are masked, server frames are not.
+    pub fn encode(&self, mask: Option<[u8; 4]>) -> Vec<u8> {
+        let mut frame = Vec::with_capacity(self.payload.len() + 14);
+        frame.push(u8::from(self.fin) << 7 | self.opcode.bits());
+        let mask_bit = if mask.is_some() { 0x80 } else { 0 };
+        match self.payload.len() {
+            len @ 0..=125 => frame.push(mask_bit | len as u8),
+            len @ 126..=0xFFFF => {
+                frame.push(mask_bit | 126);
+                frame.extend_from_slice(&(len as u16).to_be_bytes());
+            }
+            len => {
+                frame.push(mask_bit | 127);
+                frame.extend_from_slice(&(len as u64).to_be_bytes());
+            }
+        }
+        match mask {
+            Some(mask) => {
+                frame.extend_from_slice(&mask);
+                frame.extend(
+                    self.payload
+                        .iter()
+                        .enumerate()
+                        .map(|(i, b)| b ^ mask[i % 4]),
+                );
+            }
+            None => frame.extend_from_slice(&self.payload),
+        }
+        frame
+    }
+
+    /// The frame at the start of `buf`, whether it was masked and its encoded length,
+    /// or `None` when `buf` holds only a part of it.
+    pub fn decode(buf: &[u8], max_payload: usize) -> anyhow::Result<Option<(Self, bool, usize)>> {
+        if buf.len() < 2 {
+            return Ok(None);
+        }
+        if buf[0] & 0x70 != 0 {
+            return Err(anyhow::Error::msg(
+                "WebSocket frame has RSV bits without a negotiated extension",
+            ));
+        }
+        let fin = buf[0] & 0x80 != 0;
+        let opcode = OpCode::from_bits(buf[0] & 0x0F)?;
+        let masked = buf[1] & 0x80 != 0;
+        let (len, mut offset) = match buf[1] & 0x7F {
+            126 if buf.len() >= 4 => (u16::from_be_bytes([buf[2], buf[3]]) as u64, 4),
+            127 if buf.len() >= 10 => (u64::from_be_bytes(buf[2..10].try_into()?), 10),
+            126 | 127 => return Ok(None),
+            len => (len as u64, 2),
+        };
+        if opcode.is_control() && (len > 125 || !fin) {
+            return Err(anyhow::Error::msg(
+                "WebSocket control frame is fragmented or too long",
+            ));
+        }
+        let len = usize::try_from(len)
+            .ok()
+            .filter(|len| *len <= max_payload)
+            .ok_or_else(|| anyhow::Error::msg("WebSocket frame is too large"))?;
+        let mask = if masked {
+            let Some(mask) = buf.get(offset..offset + 4) else {
+                return Ok(None);
+            };
+            offset += 4;
+            Some([mask[0], mask[1], mask[2], mask[3]])
+        } else {
+            None
+        };
+        let Some(payload) = buf.get(offset..offset + len) else {
+            return Ok(None);
+        };
+        let payload = match mask {
+            Some(mask) => payload
+                .iter()
+                .enumerate()
+                .map(|(i, b)| b ^ mask[i % 4])
+                .collect(),
+            None => payload.to_vec(),
+        };
+        Ok(Some((
+            Self {
+                fin,
+                opcode,
+                payload,
+            },
+            masked,
+            offset + len,
+        )))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn try_frame_round_trip() {
+        let frame = Frame::new(OpCode::Binary, vec![7; 300]);
+        let encoded = frame.encode(Some([1, 2, 3, 4]));
+        assert_eq!([0x82, 0x80 | 126, 1, 44], encoded[..4]);
+        assert_eq!(
+            None,
+            Frame::decode(&encoded[..encoded.len() - 1], 1024).unwrap()
+        );
+        assert_eq!(
+            Some((frame, true, encoded.len())),
+            Frame::decode(&encoded, 1024).unwrap()
+        );
+        assert!(Frame::decode(&encoded, 299).is_err());
+        assert_eq!(
+            b"\x81\x05Hello",
+            Frame::new(OpCode::Text, "Hello").encode(None).as_slice()
+        );
+        assert!(Frame::decode(b"\x09\x00", 1024).is_err());
+    }
+}
@@ -0,0 +1,401 @@
+//! WebSocket client of RFC 6455 over a connection upgraded by [`Context`].
+
+mod frame;
+
+use self::frame::{Frame, OpCode};
+use crate::http::headers::is_token;
+use crate::http::Context;
+use crate::{HeaderMap, HttpHeader, Method, Socket};
+use anyhow::Context as AnyHowContext;
+use base64::Engine;
+use sha1::{Digest, Sha1};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+
+/// Appended to the key to compute Sec-WebSocket-Accept, see RFC 6455 §1.3.
+const ACCEPT_GUID: &str = "258EAFA5-E914-47DA-95CA-C5AB0DC85B11";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Message {
+    Text(String),
+    Binary(Vec<u8>),
+    Ping(Vec<u8>),
+    Pong(Vec<u8>),
+    /// Status code and reason, `None` when the peer sent no status.
+    Close(Option<(u16, String)>),
+}
+
+/// Connection after a successful `Upgrade: websocket` handshake.
+///
+/// Pings are answered automatically and still returned by [`WebSocket::recv`].
+#[derive(Debug)]
+pub struct WebSocket<S: Socket = TcpStream> {
+    socket: S,
+    /// Bytes received but not decoded yet, starting with those read ahead during the handshake.
+    buf: Vec<u8>,
+    protocol: Option<String>,
+    fragments: Option<(OpCode, Vec<u8>)>,
+    max_message_size: usize,
+    close_sent: bool,
+    closed: bool,
+}
+
+impl WebSocket {
+    /// Connects to a `ws://` URL and performs the handshake.
+    pub async fn connect(url: impl AsRef<str>, protocols: &[&str]) -> anyhow::Result<Self> {
+        Context::new(url)
+            .await?
+            .websocket(&HeaderMap::new(), protocols)
+            .await
+    }
+}
+
+impl<S: Socket> WebSocket<S> {
+    fn new(socket: S, buf: Vec<u8>, protocol: Option<String>) -> Self {
+        Self {
+            socket,
+            buf,
+            protocol,
+            fragments: None,
+            max_message_size: 64 * 1024 * 1024,
+            close_sent: false,
+            closed: false,
+        }
+    }
+
+    /// Larger messages, whole or reassembled from fragments, fail [`WebSocket::recv`].
+    pub fn with_max_message_size(mut self, max_message_size: usize) -> Self {
+        self.max_message_size = max_message_size;
+        self
+    }
+
+    /// Subprotocol selected by the server.
+    pub fn protocol(&self) -> Option<&str> {
+        self.protocol.as_deref()
+    }
+
+    pub async fn send(&mut self, message: Message) -> anyhow::Result<()> {
+        let frame = match message {
+            Message::Text(text) => Frame::new(OpCode::Text, text),
+            Message::Binary(data) => Frame::new(OpCode::Binary, data),
+            Message::Ping(data) => Frame::new(OpCode::Ping, data),
+            Message::Pong(data) => Frame::new(OpCode::Pong, data),
+            Message::Close(status) => {
+                let mut payload = vec![];
+                if let Some((code, reason)) = status {
+                    payload.extend_from_slice(&code.to_be_bytes());
+                    payload.extend_from_slice(reason.as_bytes());
+                }
+                self.close_sent = true;
+                Frame::new(OpCode::Close, payload)
+            }
+        };
+        if frame.opcode.is_control() && frame.payload.len() > 125 {
+            return Err(anyhow::Error::msg(
+                "WebSocket control frame payload exceeds 125 bytes",
+            ));
+        }
+        self.write_frame(&frame).await
+    }
+
+    /// Starts the closing handshake, [`WebSocket::recv`] returns the reply of the server.
+    pub async fn close(&mut self, code: u16, reason: &str) -> anyhow::Result<()> {
+        if self.close_sent {
+            return Ok(());
+        }
+        self.send(Message::Close(Some((code, reason.to_owned()))))
+            .await
+    }
+
+    /// Returns `None` once the connection is closed.
+    pub async fn recv(&mut self) -> anyhow::Result<Option<Message>> {
+        while !self.closed {
+            let Some(frame) = self.read_frame().await? else {
+                self.closed = true;
+                break;
+            };
+            match frame.opcode {
+                OpCode::Ping => {
+                    if !self.close_sent {
+                        self.write_frame(&Frame::new(OpCode::Pong, frame.payload.clone()))
+                            .await?;
+                    }
+                    return Ok(Some(Message::Ping(frame.payload)));
+                }
+                OpCode::Pong => return Ok(Some(Message::Pong(frame.payload))),
+                OpCode::Close => {
+                    let status = close_status(&frame.payload)?;
+                    if !self.close_sent {
+                        let code = status.as_ref().map(|(code, _)| (*code, String::new()));
+                        self.send(Message::Close(code)).await?;
+                    }
+                    self.closed = true;
+                    return Ok(Some(Message::Close(status)));
+                }
+                OpCode::Text | OpCode::Binary => {
+                    if self.fragments.is_some() {
+                        return Err(anyhow::Error::msg(
+                            "WebSocket data frame inside a fragmented message",
+                        ));
+                    }
+                    if frame.fin {
+                        return message(frame.opcode, frame.payload).map(Some);
+                    }
+                    self.fragments = Some((frame.opcode, frame.payload));
+                }
+                OpCode::Continuation => {
+                    let (opcode, mut data) = self.fragments.take().ok_or_else(|| {
+                        anyhow::Error::msg("WebSocket continuation frame without a message")
+                    })?;
+                    if data.len() + frame.payload.len() > self.max_message_size {
+                        return Err(anyhow::Error::msg("WebSocket message is too large"));
+                    }
+                    data.extend_from_slice(&frame.payload);
+                    if frame.fin {
+                        return message(opcode, data).map(Some);
+                    }
+                    self.fragments = Some((opcode, data));
+                }
+            }
+        }
+        Ok(None)
+    }
+
+    async fn write_frame(&mut self, frame: &Frame) -> anyhow::Result<()> {
+        let mask = random_bytes()?;
+        self.socket
+            .write_all(&frame.encode(Some(mask)))
+            .await
+            .context("send WebSocket frame")
+    }
+
+    /// Returns `None` when the connection closes between frames.
+    async fn read_frame(&mut self) -> anyhow::Result<Option<Frame>> {
+        let mut chunk = [0; 4096];
+        loop {
+            if let Some((frame, masked, len)) = Frame::decode(&self.buf, self.max_message_size)? {
+                if masked {
+                    return Err(anyhow::Error::msg("WebSocket server frame is masked"));
+                }
+                self.buf.drain(..len);
+                return Ok(Some(frame));
+            }
+            let n = self
+                .socket
+                .read(&mut chunk)
+                .await
+                .context("receive WebSocket frame")?;
+            if n == 0 {
+                return if self.buf.is_empty() {
+                    Ok(None)
+                } else {
+                    Err(anyhow::Error::msg(
+                        "connection closed inside a WebSocket frame",
+                    ))
+                };
+            }
+            self.buf.extend_from_slice(&chunk[..n]);
+        }
+    }
+}
+
+fn message(opcode: OpCode, data: Vec<u8>) -> anyhow::Result<Message> {
+    if opcode == OpCode::Text {
+        String::from_utf8(data)
+            .map(Message::Text)
+            .context("WebSocket text message is not UTF-8")
+    } else {
+        Ok(Message::Binary(data))
+    }
+}
+
+fn close_status(payload: &[u8]) -> anyhow::Result<Option<(u16, String)>> {
+    match payload {
+        [] => Ok(None),
+        [high, low, reason @ ..] => Ok(Some((
+            u16::from_be_bytes([*high, *low]),
+            String::from_utf8(reason.to_vec()).context("WebSocket close reason is not UTF-8")?,
+        ))),
+        _ => Err(anyhow::Error::msg(
+            "WebSocket close frame has a 1-byte payload",
+        )),
+    }
+}
+
+/// Bytes from the OS random source, masking keys must not be predictable (RFC 6455 §5.3).
+fn random_bytes<const N: usize>() -> anyhow::Result<[u8; N]> {
+    let mut bytes = [0; N];
+    getrandom::getrandom(&mut bytes)
+        .map_err(|e| anyhow::Error::msg(format!("read the OS random source: {}", e)))?;
+    Ok(bytes)
+}
+
+/// Sec-WebSocket-Accept expected for the key.
+fn accept_key(key: &str) -> String {
+    let digest = Sha1::digest(format!("{}{}", key, ACCEPT_GUID));
+    base64::engine::general_purpose::STANDARD.encode(digest)
+}
+
+impl<S: Socket> Context<S> {
+    /// Sends a GET with the upgrade headers and `headers`, checks the 101 response
+    /// and switches the connection to WebSocket.
+    pub async fn websocket(
+        mut self,
+        headers: &HeaderMap,
+        protocols: &[&str],
+    ) -> anyhow::Result<WebSocket<S>> {
+        if let Some(protocol) = protocols.iter().find(|protocol| !is_token(protocol)) {
+            return Err(anyhow::Error::msg(format!(
+                "subprotocol {:?} is not a token",
+                protocol
+            )));
+        }
+        let nonce: [u8; 16] = random_bytes()?;
+        let key = base64::engine::general_purpose::STANDARD.encode(nonce);
+
+        self.begin_request(Method::Get).await?;
+        self.request_header(HttpHeader::Upgrade(vec!["websocket".to_owned()]))
+            .await?;
+        self.request_header(HttpHeader::Connection(vec!["Upgrade".to_owned()]))
+            .await?;
+        let mut upgrade = HeaderMap::new();
+        upgrade.append("Sec-WebSocket-Key", key.as_str())?;
+        upgrade.append("Sec-WebSocket-Version", "13")?;
+        if !protocols.is_empty() {
+            upgrade.append("Sec-WebSocket-Protocol", protocols.join(", "))?;
+        }
+        self.request_headers(&upgrade).await?;
+        self.request_headers(headers).await?;
+        self.request_headers_end().await?;
+        self.end_request();
+
+        self.response_begin().await?;
+        let code = self.status()?.code;
+        if code != 101 {
+            return Err(anyhow::Error::msg(format!(
+                "WebSocket handshake answered with status {}",
+                code
+            )));
+        }
+        let response = self.response_headers();
+        let has_token = |name: &str, token: &str| {
+            response
+                .get_list(name)
+                .iter()
+                .any(|value| value.eq_ignore_ascii_case(token))
+        };
+        if !has_token("upgrade", "websocket") || !has_token("connection", "upgrade") {
+            return Err(anyhow::Error::msg(
+                "101 response does not upgrade to websocket",
+            ));
+        }
+        if response.get_str("sec-websocket-accept") != Some(accept_key(&key).as_str()) {
+            return Err(anyhow::Error::msg(
+                "Sec-WebSocket-Accept does not match the key",
+            ));
+        }
+        let protocol = response
+            .get_str("sec-websocket-protocol")
+            .map(str::to_owned);
+        if let Some(protocol) = &protocol {
+            if !protocols.contains(&protocol.as_str()) {
+                return Err(anyhow::Error::msg(format!(
+                    "server selected subprotocol {:?} that was not offered",
+                    protocol
+                )));
+            }
+        }
+        let (socket, read_ahead) = self.into_socket_parts();
+        Ok(WebSocket::new(socket, read_ahead, protocol))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    async fn server_frame(server: &mut tokio::io::DuplexStream) -> Frame {
+        let mut buf = vec![];
+        loop {
+            if let Some((frame, masked, _)) = Frame::decode(&buf, 1024).unwrap() {
+                assert!(masked);
+                return frame;
+            }
+            buf.push(server.read_u8().await.unwrap());
+        }
+    }
+
+    #[test]
+    fn try_accept_key() {
+        assert_eq!(
+            "s3pPLMBiTxaQ9kYGzzhZRbK+xOo=",
+            accept_key("dGhlIHNhbXBsZSBub25jZQ==")
+        );
+    }
+
+    #[tokio::test]
+    async fn try_websocket() {
+        let (client, mut server) = tokio::io::duplex(4096);
+        let server = tokio::spawn(async move {
+            let mut request = vec![];
+            while !request.ends_with(b"\r\n\r\n") {
+                request.push(server.read_u8().await.unwrap());
+            }
+            let request = String::from_utf8(request).unwrap();
+            assert!(request.starts_with("GET /chat HTTP/1.1\r\nUpgrade: websocket\r\n"));
+            assert!(request.contains("Sec-WebSocket-Protocol: chat, superchat\r\n"));
+            let key = request
+                .split("\r\n")
+                .find_map(|line| line.strip_prefix("Sec-WebSocket-Key: "))
+                .unwrap();
+            let mut response = format!(
+                "HTTP/1.1 101 Switching Protocols\r\nUpgrade: websocket\r\nConnection: Upgrade\r\nSec-WebSocket-Accept: {}\r\nSec-WebSocket-Protocol: chat\r\n\r\n",
+                accept_key(key)
+            )
+            .into_bytes();
+            let mut first = Frame::new(OpCode::Text, "hel");
+            first.fin = false;
+            response.extend(first.encode(None));
+            response.extend(Frame::new(OpCode::Ping, "p").encode(None));
+            response.extend(Frame::new(OpCode::Continuation, "lo").encode(None));
+            server.write_all(&response).await.unwrap();
+
+            assert_eq!(
+                Frame::new(OpCode::Pong, "p"),
+                server_frame(&mut server).await
+            );
+            assert_eq!(
+                Frame::new(OpCode::Binary, vec![1, 2]),
+                server_frame(&mut server).await
+            );
+            assert_eq!(
+                Frame::new(OpCode::Close, b"\x03\xe8bye".to_vec()),
+                server_frame(&mut server).await
+            );
+            server
+                .write_all(&Frame::new(OpCode::Close, b"\x03\xe8".to_vec()).encode(None))
+                .await
+                .unwrap();
+        });
+
+        let url = url::Url::parse("ws://example.org/chat").unwrap();
+        let mut ws = Context::from_socket(url, client)
+            .websocket(&HeaderMap::new(), &["chat", "superchat"])
+            .await
+            .unwrap();
+        assert_eq!(Some("chat"), ws.protocol());
+        assert_eq!(Some(Message::Ping(b"p".to_vec())), ws.recv().await.unwrap());
+        assert_eq!(
+            Some(Message::Text("hello".to_owned())),
+            ws.recv().await.unwrap()
+        );
+        ws.send(Message::Binary(vec![1, 2])).await.unwrap();
+        ws.close(1000, "bye").await.unwrap();
+        assert_eq!(
+            Some(Message::Close(Some((1000, String::new())))),
+            ws.recv().await.unwrap()
+        );
+        assert_eq!(None, ws.recv().await.unwrap());
+        server.await.unwrap();
+    }
+}
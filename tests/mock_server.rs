@@ -0,0 +1,124 @@
+use http_chunked::http::framing::TruncatedBody;
+use http_chunked::http::Context;
+use http_chunked::test_util::{MockResponse, MockServer};
+use http_chunked::{HttpHeader, Method, TcpConnector};
+use std::time::Duration;
+
+async fn get(server: &MockServer, path: &str) -> Context {
+    let mut http = Context::with_connector(server.url(path), &TcpConnector::default())
+        .await
+        .unwrap();
+    http.begin_request(Method::Get).await.unwrap();
+    http.request_headers_end().await.unwrap();
+    http.response_begin().await.unwrap();
+    http
+}
+
+#[tokio::test]
+async fn try_chunked_splits_and_trailers() {
+    let server = MockServer::start().await.unwrap();
+    server.enqueue(
+        MockResponse::new(200, "OK")
+            .header("Trailer", "Checksum")
+            .chunked()
+            .raw("5;na")
+            .flush()
+            .raw("me=value\r\nhel")
+            .pause(Duration::from_millis(20))
+            .raw("lo")
+            .flush()
+            .raw("\r\n")
+            .chunk_with_extension(" world", "last")
+            .last_chunk(&[("Checksum", "abc")]),
+    );
+
+    let mut http = get(&server, "/chunks").await;
+    assert_eq!(b"hello world", http.bytes().await.unwrap().as_slice());
+    assert_eq!(
+        vec![HttpHeader::Custom {
+            name: "Checksum".to_owned(),
+            value: "abc".to_owned(),
+        }],
+        http.response_trailer_iter().collect::<Vec<_>>()
+    );
+}
+
+#[tokio::test]
+async fn try_abrupt_close() {
+    let server = MockServer::start().await.unwrap();
+    server.enqueue(
+        MockResponse::new(200, "OK")
+            .header("Content-Length", "10")
+            .raw("\r\nab")
+            .pause(Duration::from_millis(20))
+            .raw("c")
+            .close(),
+    );
+
+    let mut http = get(&server, "/").await;
+    let e = http.bytes().await.unwrap_err();
+    assert_eq!(
+        Some(&TruncatedBody {
+            received: 3,
+            expected: Some(10),
+        }),
+        e.downcast_ref()
+    );
+}
+
+#[tokio::test]
+async fn try_recorded_requests() {
+    let server = MockServer::start().await.unwrap();
+    server.enqueue(MockResponse::with_body(201, "Created", "{}"));
+    server.enqueue(MockResponse::with_body(200, "OK", "second"));
+
+    let mut http = Context::with_connector(server.url("/items"), &TcpConnector::default())
+        .await
+        .unwrap();
+    http.begin_request(Method::Post).await.unwrap();
+    http.request_header(HttpHeader::ContentLength(9))
+        .await
+        .unwrap();
+    http.request_headers_end().await.unwrap();
+    http.request_body_chunk("{\"id\":1}\n").await.unwrap();
+    http.response_begin().await.unwrap();
+    assert_eq!(201, http.status().unwrap().code);
+    assert_eq!(b"{}", http.bytes().await.unwrap().as_slice());
+
+    http.set_resource("/items", Some("page=2"));
+    http.begin_request(Method::Get).await.unwrap();
+    http.request_headers_end().await.unwrap();
+    http.response_begin().await.unwrap();
+    assert_eq!(b"second", http.bytes().await.unwrap().as_slice());
+
+    let requests = server.requests();
+    assert_eq!(2, requests.len());
+    assert_eq!(
+        (Method::Post, "/items", b"{\"id\":1}\n".as_slice()),
+        (
+            requests[0].method,
+            requests[0].target.as_str(),
+            requests[0].body.as_slice()
+        )
+    );
+    assert_eq!(
+        Some(server.addr().to_string().as_str()),
+        requests[0].headers.get_str("host")
+    );
+    assert_eq!("/items?page=2", requests[1].target);
+}
+
+#[tokio::test]
+async fn try_trickled_response() {
+    let server = MockServer::start().await.unwrap();
+    server.enqueue(
+        MockResponse::new(200, "OK")
+            .chunked()
+            .chunk("slow")
+            .last_chunk(&[])
+            .trickle(Duration::from_millis(1)),
+    );
+
+    let mut http = get(&server, "/").await;
+    assert_eq!(b"slow", http.bytes().await.unwrap().as_slice());
+}